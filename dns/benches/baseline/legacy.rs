@@ -0,0 +1,32 @@
+// The codec benchmarks written against the bitvec/nom implementation that the byte cursor
+// replaced. run.sh copies this file into a checkout of that commit, it isn't built here
+use bitvec::bitvec;
+use bitvec::order::Msb0;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use dns::pkt::message::Message;
+use dns::pkt::Serializable;
+
+fn bench(c: &mut Criterion) {
+    let packets = [("cname_chain", include_bytes!("../raw_pkt").to_vec()),
+        ("referral", include_bytes!("referral.bin").to_vec())];
+    let mut group = c.benchmark_group("parse");
+    for (name, pkt) in packets.iter() {
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| Message::deserialize(black_box(pkt)).unwrap()));
+    }
+    group.finish();
+    let mut group = c.benchmark_group("encode");
+    for (name, pkt) in packets.iter() {
+        let m = Message::deserialize(pkt).unwrap();
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| {
+            let mut data = bitvec![u8, Msb0;];
+            black_box(&m).serialize(&mut data).unwrap();
+            data.into_vec()
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
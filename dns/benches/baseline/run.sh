@@ -0,0 +1,28 @@
+#!/bin/sh
+# Runs the parse and encode benchmarks against the bitvec/nom codec, so its numbers can be compared
+# with `cargo bench --bench codec`. Usage: run.sh [revision] [criterion arguments]. The revision
+# is the last commit with that codec, the codec-baseline tag unless given. That tree has no library
+# target, one is added to the temporary checkout
+set -e
+here=$(cd "$(dirname "$0")" && pwd)
+rev=${1:-codec-baseline}
+[ $# -gt 0 ] && shift
+base=$(git -C "$here" rev-parse --verify "$rev^{commit}")
+dir=$(mktemp -d)
+trap 'git -C "$here" worktree remove --force "$dir"' EXIT
+git -C "$here" worktree add --detach "$dir" "$base"
+cd "$dir/dns"
+echo 'pub mod pkt;' > src/lib.rs
+cat >> Cargo.toml <<'TOML'
+
+[dev-dependencies]
+criterion = "0.5"
+
+[[bench]]
+name = "legacy"
+harness = false
+TOML
+mkdir -p benches
+cp "$here/legacy.rs" benches/
+cp "$here/../referral.bin" benches/
+CARGO_TARGET_DIR="$here/../../target/baseline" cargo bench --bench legacy "$@"
@@ -0,0 +1,57 @@
+// Parsing and encoding of a captured CNAME chain (raw_pkt) and a referral with EDNS and glue
+// (referral.bin). `baseline/run.sh` runs the parse and encode groups against the bitvec/nom codec
+// the byte cursor replaced, over the same packets. Median times from one machine:
+//
+//                          bitvec/nom   byte cursor
+//   parse/cname_chain         4.66us        1.34us
+//   parse/referral            9.18us        4.96us
+//   encode/cname_chain       14.3us         1.06us
+//   encode/referral          29.3us         1.37us
+//
+// parse_ref (0.31us and 0.72us) and encode_into (0.77us and 1.23us) have no counterpart there
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use dns::{Message, MessageRef, Serializable};
+
+fn bench(c: &mut Criterion) {
+    let packets = [("cname_chain", include_bytes!("../raw_pkt").to_vec()),
+        ("referral", include_bytes!("referral.bin").to_vec())];
+    let mut group = c.benchmark_group("parse");
+    for (name, pkt) in packets.iter() {
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| Message::deserialize(black_box(pkt)).unwrap()));
+    }
+    group.finish();
+    // Walks every record and label without copying any of them
+    let mut group = c.benchmark_group("parse_ref");
+    for (name, pkt) in packets.iter() {
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| {
+            let m = MessageRef::new(black_box(pkt)).unwrap();
+            let records = m.answers().chain(m.authority()).chain(m.additional());
+            records.map(|r| r.unwrap().name().labels().count()).sum::<usize>()
+        }));
+    }
+    group.finish();
+    let mut group = c.benchmark_group("encode");
+    for (name, pkt) in packets.iter() {
+        let m = Message::deserialize(pkt).unwrap();
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| {
+            let mut data = vec![];
+            black_box(&m).serialize(&mut data).unwrap();
+            data
+        }));
+    }
+    group.finish();
+    let mut group = c.benchmark_group("encode_into");
+    for (name, pkt) in packets.iter() {
+        let m = Message::deserialize(pkt).unwrap();
+        let mut buf = [0u8; 512];
+        group.throughput(Throughput::Bytes(pkt.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| black_box(&m).encode_into(&mut buf).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+mod pkt;
+
+pub use pkt::answer::Answer;
+pub use pkt::builder::MessageBuilder;
+pub use pkt::dnssec::{Algorithm, DigestType, Dnskey, Ds, Nsec, Nsec3, Nsec3Param, Rrsig, TypeBitmap};
+pub use pkt::edns::{Edns, EdnsOption};
+pub use pkt::error::{DnsError, DnsErrorKind, EncodeError, TsigError, ValidationError};
+pub use pkt::header::{Flags, Header, Opcode, Rcode};
+pub use pkt::message::Message;
+pub use pkt::message_ref::{AnswerRef, Labels, MessageRef, NameRef, QuestionRef, Section};
+pub use pkt::name::{IntoName, Name};
+pub use pkt::question::{Qclass, Qtype, Question};
+pub use pkt::rdata::RData;
+pub use pkt::zone::{load_zone, parse_zone, ZoneError, ZoneErrorKind};
+pub use pkt::validate::{sign_rrset, verify_ds, verify_rrset};
+pub use pkt::denial::{nsec3_hash, Denial};
+pub use pkt::resolver::{exchange, ResolveError, Resolver, Response, Security, ROOT_ANCHOR};
+pub use pkt::tsig::{Tsig, TsigAlgorithm, TsigKey, TsigRcode, TsigSigner, TsigVerifier};
+pub use pkt::update::UpdateBuilder;
+pub use pkt::Serializable;
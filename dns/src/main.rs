@@ -3,35 +3,44 @@ extern crate core;
 use crate::udp::{send_dns_q};
 use std::fs::OpenOptions;
 use std::io::Write;
-use bitvec::bitvec;
-use bitvec::order::Msb0;
-use crate::pkt::message::Message;
-use crate::pkt::Serializable;
+use dns::{Message, Qclass, Qtype, Serializable};
 
 pub mod udp;
-pub mod pkt;
 
 fn main() {
-    let message = Message::build(1337,
-                                       "www.northeastern.edu",
-                                       "A");
+    let message = match Message::builder()
+        .id(1337)
+        .rd(true)
+        .question("www.northeastern.edu", Qtype::A, Qclass::IN)
+        .build() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("unable to build query: {}", e);
+            return;
+        }
+    };
 
-    let mut bv = bitvec![u8, Msb0;];
-    message.serialize(&mut bv);
-    let vector = bv.into_vec();
+    let mut vector = vec![];
+    if let Err(e) = message.serialize(&mut vector) {
+        eprintln!("unable to encode query: {}", e);
+        return;
+    }
     write_to_file("request", &vector);
-    let m = send_dns_q(&vector);
-    println!("{}", m);
+    match send_dns_q(&vector) {
+        Ok(m) => println!("{}", m),
+        Err(e) => eprintln!("unable to parse response: {}", e),
+    }
     //write_to_file("out", rsp.to_vec())
 }
 
-fn write_to_file(filename: &str, v: &Vec<u8>) {
+fn write_to_file(filename: &str, v: &[u8]) {
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(filename)
         .expect("unable to write to file");
 
-    file.write_all(&v).expect("unable to write to file");
+    file.write_all(v).expect("unable to write to file");
 
 }
\ No newline at end of file
@@ -1,86 +1,181 @@
-use nom::bits::complete::take;
-use bitvec::prelude::{BitVec, Msb0};
-use nom::combinator::peek;
-use nom::{IResult};
-use nom::multi::count;
-
-pub mod pkt;
-pub mod header;
-pub mod question;
-pub mod message;
-pub mod answer;
+use crate::pkt::codec::Reader;
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+use crate::pkt::name::Name;
+
+pub(crate) mod header;
+pub(crate) mod question;
+pub(crate) mod message;
+pub(crate) mod answer;
+pub(crate) mod error;
+pub(crate) mod rdata;
+pub(crate) mod compress;
+pub(crate) mod name;
+pub(crate) mod edns;
+pub(crate) mod codec;
+pub(crate) mod message_ref;
+pub(crate) mod builder;
+pub(crate) mod text;
+pub(crate) mod zone;
+pub(crate) mod dnssec;
+pub(crate) mod validate;
+pub(crate) mod denial;
+pub(crate) mod resolver;
+pub(crate) mod tsig;
+pub(crate) mod update;
+#[cfg(feature = "serde")]
+pub(crate) mod json;
 
 const PTR_OFFSET: u8 = 0b11000000;
 
 pub trait Serializable {
-    // Takes an Object and serializes the data into `data`
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>);
-
-    // takes data and generates an Object, consuming the buffer
-    //fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Self>;
+    // Takes an Object and serializes the data into `data`, nothing is written when it can't be encoded
+    fn serialize(&self, data: &mut Vec<u8>) -> Result<(), EncodeError>;
 }
 
-pub type NBitSlice<'a> = (&'a [u8], usize);
-
-pub fn take_u1(data: NBitSlice) -> IResult<NBitSlice, bool> {
-    let (res, b): (NBitSlice, u8) = take(1u8)(data)?;
-    Ok((res, if b > 0 { true } else { false }))
+// Reads a possibly compressed name
+pub(crate) fn parse_name(data: &mut Reader) -> Result<Name, DnsError> {
+    let mut name = Name::root();
+    walk_name(data, |label, at| name.push_label(label.to_vec(), at))?;
+    Ok(name)
 }
 
-pub fn take_u4(data: NBitSlice) -> IResult<NBitSlice, u8> {
-    take(4u8)(data)
+// Hands each label of a possibly compressed name to `visit` along with its offset, leaving `data`
+// just past the name. Pointers are followed iteratively and every jump must land before the
+// labels read since the last one, which rules out forward pointers and cycles
+pub(crate) fn walk_name<'a>(data: &mut Reader<'a>,
+                            mut visit: impl FnMut(&'a [u8], usize) -> Result<(), DnsError>) -> Result<(), DnsError> {
+    // Where the caller carries on reading, set by the first pointer we follow
+    let mut rest = None;
+    let mut pos = *data;
+    let mut run_start = data.position();
+    loop {
+        // A label can end with a ptr, recheck the ptr math every loop
+        let first_byte = pos.peek_u8()?;
+        match first_byte & PTR_OFFSET {
+            PTR_OFFSET => {
+                let at = pos;
+                let target = get_deref_ptr(pos.read_u16()?);
+                if target >= pos.len() {
+                    return Err(at.error(DnsErrorKind::PointerOutOfRange(target)));
+                }
+                if target >= at.position() {
+                    return Err(at.error(DnsErrorKind::ForwardPointer(target)));
+                }
+                if target >= run_start {
+                    return Err(at.error(DnsErrorKind::PointerLoop(target)));
+                }
+                rest.get_or_insert(pos.position());
+                run_start = target;
+                pos.seek(target);
+            }
+            0 => {
+                let at = pos.position();
+                let size = pos.read_u8()?;
+                if size == 0 {
+                    break;
+                }
+                visit(pos.read_bytes(size as usize)?, at)?;
+            }
+            // 0b01 and 0b10 are reserved (RFC 1035 section 4.1.4, RFC 6891 section 5)
+            _ => return Err(pos.error(DnsErrorKind::ReservedLabelType(first_byte >> 6))),
+        }
+    }
+    data.seek(rest.unwrap_or(pos.position()));
+    Ok(())
 }
 
-pub fn take_u8(data: NBitSlice) -> IResult<NBitSlice, u8> {
-    take(8u8)(data)
+// Steps over a name without following its pointers, only the framing is checked
+pub(crate) fn skip_name(data: &mut Reader) -> Result<(), DnsError> {
+    loop {
+        let first_byte = data.peek_u8()?;
+        match first_byte & PTR_OFFSET {
+            PTR_OFFSET => {
+                data.read_u16()?;
+                return Ok(());
+            }
+            0 => {
+                let size = data.read_u8()?;
+                if size == 0 {
+                    return Ok(());
+                }
+                data.read_bytes(size as usize)?;
+            }
+            _ => return Err(data.error(DnsErrorKind::ReservedLabelType(first_byte >> 6))),
+        }
+    }
 }
 
-pub fn take_u3(data: NBitSlice) -> IResult<NBitSlice, u8> {
-    take(3u8)(data)
+pub(crate) fn get_deref_ptr(ptr: u16) -> usize {
+    (ptr & !((PTR_OFFSET as u16) << 8)) as usize
 }
 
-pub fn take_u16(data: NBitSlice) -> IResult<NBitSlice, u16> {
-    take(16u16)(data)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses a name placed right after a blank 12 byte header
+    fn parse(name: &[u8], at: usize) -> Result<(Name, usize), DnsError> {
+        let mut data = vec![0u8; 12];
+        data.extend_from_slice(name);
+        let mut r = Reader::new(&data);
+        r.seek(at);
+        let name = parse_name(&mut r)?;
+        Ok((name, r.position()))
+    }
 
-pub fn take_u32(data: NBitSlice) -> IResult<NBitSlice, u32> {
-    take(32u8)(data)
-}
+    fn kind(name: &[u8], at: usize) -> DnsErrorKind {
+        parse(name, at).unwrap_err().kind
+    }
 
-pub fn take_bytes(data: NBitSlice, bytes: usize) -> IResult<NBitSlice, Vec<u8>> {
-    count(take(8u8), bytes)(data)
-}
+    #[test]
+    fn follows_pointers() {
+        let data = b"\x07example\x03com\x00\x03www\xc0\x0c";
+        let (name, end) = parse(data, 25).unwrap();
+        assert_eq!(name.to_string(), "www.example.com.");
+        assert_eq!(end, 12 + data.len());
+    }
 
-pub fn parse_name<'a>(mut data: (&'a [u8], usize), raw_data: &[u8]) -> IResult<NBitSlice<'a>, String> {
-    let mut name = String::new();
-    loop {
-        // A label can end with a ptr, recheck the ptr math every loop
-        let (_, first_byte): (NBitSlice, u8) = peek(take(8u8))(data)?;
-        if first_byte & PTR_OFFSET > 0 {
-            let (rem, ptr) = take_u16(data).unwrap();
-            let next: NBitSlice = (&raw_data[get_deref_ptr(ptr)..], 0);
-            let (_, part) = parse_name(next, raw_data).unwrap();
-            data = rem;
-            name += &part;
-            break;
-        } else {
-            let (rem, size) = take_u8(data).unwrap();
-            data = rem;
-            if size == 0 {
-                break;
-            }
-            name.push('.');
+    #[test]
+    fn pointer_loop() {
+        assert_eq!(kind(b"\x01a\xc0\x0c", 12), DnsErrorKind::PointerLoop(12));
+        // Each jump goes backwards, but the second lands on labels already read
+        assert_eq!(kind(b"\x01a\xc0\x0c\xc0\x0e", 16), DnsErrorKind::PointerLoop(12));
+    }
 
-            let (rem, buf) = take_bytes(data, size as usize)?;
-            data = rem;
-            name.push_str(&String::from_utf8(buf).expect("can't parse to utf8"));
-        }
+    #[test]
+    fn forward_pointer() {
+        assert_eq!(kind(b"\xc0\x0c", 12), DnsErrorKind::ForwardPointer(12));
+        assert_eq!(kind(b"\xc0\x0e\x00", 12), DnsErrorKind::ForwardPointer(14));
     }
-    Ok((data, name))
-}
 
+    #[test]
+    fn pointer_out_of_range() {
+        assert_eq!(kind(b"\x00\xc0\xff", 13), DnsErrorKind::PointerOutOfRange(255));
+    }
 
-fn get_deref_ptr(ptr: u16) -> usize {
-    (ptr - ((PTR_OFFSET as u16) << 8)) as usize
-}
+    #[test]
+    fn reserved_label_types() {
+        assert_eq!(kind(b"\x41a\x00", 12), DnsErrorKind::ReservedLabelType(0b01));
+        assert_eq!(kind(b"\x81a\x00", 12), DnsErrorKind::ReservedLabelType(0b10));
+    }
+
+    #[test]
+    fn name_too_long() {
+        // Four 63 byte labels take 256 bytes with their lengths and the root
+        let mut long = vec![];
+        for _ in 0..4 {
+            long.push(63);
+            long.extend_from_slice(&[b'a'; 63]);
+        }
+        long.push(0);
+        assert_eq!(kind(&long, 12), DnsErrorKind::NameTooLong);
+        assert!(parse(&long[64..], 12).is_ok());
+    }
 
+    #[test]
+    fn truncated() {
+        assert_eq!(kind(b"\x03ww", 12), DnsErrorKind::Truncated);
+        assert_eq!(kind(b"\x03www", 12), DnsErrorKind::Truncated);
+        assert_eq!(kind(b"\xc0", 12), DnsErrorKind::Truncated);
+    }
+}
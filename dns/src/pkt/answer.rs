@@ -1,64 +1,166 @@
 use std::fmt;
 use std::fmt::Formatter;
-use bitvec::order::Msb0;
-use bitvec::prelude::BitVec;
-use nom::IResult;
+use std::str::FromStr;
 use crate::pkt::question::{Qclass, Qtype};
-use crate::pkt::{NBitSlice, parse_name, Serializable, take_bytes, take_u16, take_u32};
+use crate::pkt::compress::Compressor;
+use crate::pkt::name::Name;
+use crate::pkt::rdata::RData;
+use crate::pkt::{parse_name, Serializable};
+use crate::pkt::codec::{rdata_len, Buffer, Reader};
+use crate::pkt::error::{DnsError, EncodeError};
+use crate::pkt::text::{parse_record, Defaults};
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Answer {
-    name: String,
+    name: Name,
     ty: Qtype,
     class: Qclass,
     ttl: u32,
-    rdlength: u16,
-    rddata: Vec<u8>,
-    parsed_data: String
+    rdata: RData
 }
 
+// Like a message, nothing is written when the record can't be encoded
 impl Serializable for Answer {
-    fn serialize(&self, _data: &mut BitVec<u8, Msb0>) {
-        todo!()
+    fn serialize(&self, data: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let start = data.len();
+        let result = self.serialize_with(data, &mut Compressor::disabled());
+        if result.is_err() {
+            data.truncate(start);
+        }
+        result
     }
 }
 
 
 impl Answer {
-    pub fn deserialize<'a>(data: (&'a [u8], usize), raw_data: &[u8]) -> IResult<NBitSlice<'a>, Answer> {
-        let (data, name) = parse_name(data, raw_data).unwrap();
-        let (data, ty) = Qtype::deserialize(data).unwrap();
-        let (data, class) = Qclass::deserialize(data).unwrap();
-        let (data, ttl) = take_u32(data).unwrap();
-        let (data, rdlength) = take_u16(data).unwrap();
-        let (data, rddata) = take_bytes(data, rdlength as usize).unwrap();
-        let mut ans = Answer {
+    pub fn new(name: Name, class: Qclass, ttl: u32, rdata: RData) -> Answer {
+        Answer {
+            name,
+            ty: rdata.qtype(),
+            class,
+            ttl,
+            rdata
+        }
+    }
+
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<Answer, DnsError> {
+        let name = parse_name(data)?;
+        let ty = Qtype::deserialize(data)?;
+        let class = Qclass::deserialize(data)?;
+        let ttl = data.read_u32()?;
+        let rdlength = data.read_u16()?;
+        // Parse the rdata in place so names can follow pointers and errors keep their offset
+        let mut rdata = data.limit(rdlength as usize)?;
+        let rdata = read_rdata(ty, class, &mut rdata)?;
+        Ok(Answer {
             name,
             ty,
             class,
             ttl,
-            rdlength,
-            rddata,
-            parsed_data: "".to_string()
-        };
-        ans.parse_record(raw_data);
-        Ok((data, ans))
+            rdata
+        })
+    }
+
+    pub(crate) fn serialize_with(&self, data: &mut impl Buffer, names: &mut Compressor) -> Result<(), EncodeError> {
+        names.write_name(data, &self.name)?;
+        self.ty.serialize(data)?;
+        self.class.serialize(data)?;
+        data.put_u32(self.ttl)?;
+        // The rdlength is only known once the rdata is written, so it is filled in afterwards.
+        // The received rdata may hold compression pointers into the original message,
+        // so it is always written back out from its parsed form
+        let rdlength = data.written().len();
+        data.put_u16(0)?;
+        self.rdata.serialize(data, names)?;
+        let len = rdata_len(data.written().len() - rdlength - 2)?;
+        data.put_at(rdlength, &len.to_be_bytes());
+        Ok(())
+    }
+
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn ty(&self) -> Qtype {
+        self.ty
+    }
+
+    pub fn class(&self) -> Qclass {
+        self.class
+    }
+
+    pub fn ttl(&self) -> u32 {
+        self.ttl
     }
 
-    fn parse_record(&mut self, raw_data: &[u8]) {
-        self.parsed_data =
-            match self.ty {
-            Qtype::A => { format! {"{}.{}.{}.{}", self.rddata[0], self.rddata[1], self.rddata[2], self.rddata[3]} }
-            Qtype::CNAME => {
-                let (_, name) = parse_name((&self.rddata, 0), raw_data).unwrap();
-                name
-            }
-        };
+    pub fn rdata(&self) -> &RData {
+        &self.rdata
     }
 }
 
 
+// Dynamic updates use records of class ANY or NONE without rdata to stand for a whole RRset or
+// name (RFC 2136 section 2.4), their rdata is kept as empty opaque data whatever the type
+pub(crate) fn read_rdata(ty: Qtype, class: Qclass, rdata: &mut Reader) -> Result<RData, DnsError> {
+    if rdata.is_empty() && ty != Qtype::OPT && matches!(class, Qclass::ANY | Qclass::NONE) {
+        return Ok(RData::Unknown { ty: u16::from(ty), data: vec![] });
+    }
+    RData::deserialize(&ty, rdata)
+}
+
+// Reads a whole record in presentation format, such as `example.com. 3600 IN MX 10 mail.example.com.`.
+// Names must be fully qualified and the TTL is required, the class defaults to IN
+impl FromStr for Answer {
+    type Err = DnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_record(s, &Defaults::new())
+    }
+}
+
+// The master file form (RFC 1035 section 5.1)
 impl fmt::Display for Answer {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(f, "{}\t{}\t{}\t{}\t{:?}", self.name, self.ty, self.class, self.ttl, self.parsed_data)
+        // `{:#}` shows the owner name in Unicode, the rdata stays as it would be in a zone file
+        if f.alternate() {
+            return writeln!(f, "{:#}\t{}\t{}\t{}\t{}", self.name, self.ttl, self.class, self.ty, self.rdata);
+        }
+        writeln!(f, "{}\t{}\t{}\t{}\t{}", self.name, self.ttl, self.class, self.ty, self.rdata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::pkt::message::Message;
+
+    const RAW: &[u8] = include_bytes!("../../raw_pkt");
+
+    fn to_vec(value: &impl Serializable) -> Vec<u8> {
+        let mut data = vec![];
+        value.serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn round_trip_response() {
+        let message = Message::deserialize(RAW).unwrap();
+        let data = to_vec(&message);
+        assert_eq!(data, RAW);
+        assert_eq!(Message::deserialize(&data).unwrap().to_string(), message.to_string());
+    }
+
+    #[test]
+    fn serialize_record() {
+        let record = Answer::new(Name::from_ascii("a.example.").unwrap(), Qclass::IN, 3600, RData::A(Ipv4Addr::new(192, 0, 2, 1)));
+        let data = to_vec(&record);
+        assert_eq!(data, [
+            1, b'a', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0,
+            0, 1, 0, 1, 0, 0, 0x0e, 0x10, 0, 4, 192, 0, 2, 1,
+        ]);
+        let parsed = Answer::deserialize(&mut Reader::new(&data)).unwrap();
+        assert_eq!(to_vec(&parsed), data);
     }
 }
@@ -0,0 +1,235 @@
+use crate::pkt::answer::Answer;
+use crate::pkt::edns::{Edns, EdnsOption};
+use crate::pkt::error::{DnsError, DnsErrorKind};
+use crate::pkt::header::{Opcode, Rcode};
+use crate::pkt::message::Message;
+use crate::pkt::name::IntoName;
+use crate::pkt::question::{Qclass, Qtype, Question};
+use crate::pkt::tsig::TsigSigner;
+
+// Puts a message together a piece at a time. Nothing is checked until `build`, which reports
+// the first bad name or overfull section instead of panicking
+pub struct MessageBuilder {
+    message: Message,
+    rcode: Rcode,
+    error: Option<DnsError>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> MessageBuilder {
+        MessageBuilder {
+            message: Message::new(),
+            rcode: Rcode::NoError,
+            error: None,
+        }
+    }
+
+    pub fn id(mut self, id: u16) -> Self {
+        self.message.header.id = id;
+        self
+    }
+
+    pub fn opcode(mut self, opcode: Opcode) -> Self {
+        self.message.header.opcode = opcode;
+        self
+    }
+
+    // The full 12 bit rcode, an OPT record is added for the upper bits if they're needed
+    pub fn rcode(mut self, rcode: Rcode) -> Self {
+        self.rcode = rcode;
+        self
+    }
+
+    pub fn qr(mut self, set: bool) -> Self {
+        self.message.header.qr = set;
+        self
+    }
+
+    pub fn aa(mut self, set: bool) -> Self {
+        self.message.header.aa = set;
+        self
+    }
+
+    pub fn tc(mut self, set: bool) -> Self {
+        self.message.header.tc = set;
+        self
+    }
+
+    pub fn rd(mut self, set: bool) -> Self {
+        self.message.header.rd = set;
+        self
+    }
+
+    pub fn ra(mut self, set: bool) -> Self {
+        self.message.header.ra = set;
+        self
+    }
+
+    pub fn ad(mut self, set: bool) -> Self {
+        self.message.header.ad = set;
+        self
+    }
+
+    pub fn cd(mut self, set: bool) -> Self {
+        self.message.header.cd = set;
+        self
+    }
+
+    pub fn question(mut self, name: impl IntoName, qtype: Qtype, qclass: Qclass) -> Self {
+        match name.into_name() {
+            Ok(qname) => {
+                if self.check_count("question", self.message.header.qdcount) {
+                    self.message.header.qdcount += 1;
+                    self.message.questions.push(Question { qname, qtype, qclass });
+                }
+            }
+            Err(e) => self.fail(e),
+        }
+        self
+    }
+
+    pub fn answer(mut self, record: Answer) -> Self {
+        if self.check_count("answer", self.message.header.ancount) {
+            self.message.add_answer(record);
+        }
+        self
+    }
+
+    pub fn authority(mut self, record: Answer) -> Self {
+        if self.check_count("authority", self.message.header.nscount) {
+            self.message.add_authority(record);
+        }
+        self
+    }
+
+    pub fn additional(mut self, record: Answer) -> Self {
+        if self.check_count("additional", self.message.header.arcount) {
+            self.message.add_additional(record);
+        }
+        self
+    }
+
+    // Replaces any EDNS set so far, options added earlier are dropped with it
+    pub fn edns(mut self, edns: Edns) -> Self {
+        if self.message.edns.is_some() || self.check_count("additional", self.message.header.arcount) {
+            self.message.set_edns(Some(edns));
+        }
+        self
+    }
+
+    // Adds the default EDNS first when none was set
+    pub fn edns_option(mut self, option: EdnsOption) -> Self {
+        if self.message.edns.is_none() {
+            self = self.edns(Edns::default());
+        }
+        if let Some(edns) = self.message.edns.as_mut() {
+            edns.options.push(option);
+        }
+        self
+    }
+
+    // Signs the message when it is serialized, see `Message::set_tsig`
+    pub fn tsig(mut self, signer: TsigSigner) -> Self {
+        if self.message.signer.is_some() || self.check_count("additional", self.message.header.arcount) {
+            self.message.set_tsig(Some(signer));
+        }
+        self
+    }
+
+    pub fn build(mut self) -> Result<Message, DnsError> {
+        if self.rcode.extended_bits() != 0 && self.message.edns.is_none() {
+            self = self.edns(Edns::default());
+        }
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.message.set_rcode(self.rcode);
+        Ok(self.message)
+    }
+
+    // Only the first error is kept, it is the one the caller needs to fix first
+    fn fail(&mut self, e: DnsError) {
+        self.error.get_or_insert(e);
+    }
+
+    fn check_count(&mut self, section: &'static str, count: u16) -> bool {
+        if count == u16::MAX {
+            self.fail(DnsError { kind: DnsErrorKind::TooManyRecords(section), offset: 0 });
+            return false;
+        }
+        true
+    }
+}
+
+impl Default for MessageBuilder {
+    fn default() -> Self {
+        MessageBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::pkt::name::Name;
+    use crate::pkt::rdata::RData;
+
+    #[test]
+    fn query() {
+        let message = MessageBuilder::new()
+            .id(7)
+            .rd(true)
+            .cd(true)
+            .question("example.com", Qtype::A, Qclass::IN)
+            .question("example.com", Qtype::AAAA, Qclass::IN)
+            .edns_option(EdnsOption { code: 10, data: vec![0; 8] })
+            .build()
+            .unwrap();
+        let data = message.to_vec().unwrap();
+        let parsed = Message::deserialize(&data).unwrap();
+        assert_eq!(parsed.to_vec().unwrap(), data);
+        assert_eq!(parsed.header.id, 7);
+        assert!(parsed.header.rd && parsed.header.cd && !parsed.header.qr);
+        assert_eq!(parsed.questions.len(), 2);
+        assert_eq!(parsed.edns().unwrap().options.len(), 1);
+    }
+
+    #[test]
+    fn first_error_kept() {
+        let e = MessageBuilder::new()
+            .question("a..example", Qtype::A, Qclass::IN)
+            .question("b\\", Qtype::A, Qclass::IN)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(e.kind, DnsErrorKind::EmptyLabel);
+    }
+
+    #[test]
+    fn extended_rcode_adds_opt() {
+        let message = MessageBuilder::new().qr(true).rcode(Rcode::Other(0x123)).build().unwrap();
+        assert_eq!(message.header.rcode, Rcode::NotImplemented);
+        assert_eq!(message.edns().unwrap().extended_rcode, 0x12);
+        assert_eq!(Message::deserialize(&message.to_vec().unwrap()).unwrap().rcode(), Rcode::Other(0x123));
+    }
+
+    #[test]
+    fn response_to_query() {
+        let query = MessageBuilder::new()
+            .id(99)
+            .rd(true)
+            .question("example.com", Qtype::A, Qclass::IN)
+            .edns(Edns { dnssec_ok: true, ..Edns::default() })
+            .build()
+            .unwrap();
+        let mut response = Message::response_to(&query);
+        response.add_answer(Answer::new(Name::from_ascii("example.com").unwrap(), Qclass::IN, 60,
+                                        RData::A(Ipv4Addr::new(192, 0, 2, 1))));
+        let parsed = Message::deserialize(&response.to_vec().unwrap()).unwrap();
+        assert_eq!(parsed.header.id, 99);
+        assert!(parsed.header.qr && parsed.header.rd);
+        assert_eq!(parsed.questions[0].to_string(), query.questions[0].to_string());
+        assert!(parsed.edns().unwrap().dnssec_ok);
+        assert_eq!(parsed.answers.len(), 1);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+
+// A cursor over a message being decoded. Everything in a message is byte aligned so
+// fields are read straight out of the slice, `data` always starts at the message header
+// so positions double as offsets for compression pointers and errors
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    // The bytes readable from the start of the message, pointers can't go past this
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    // Everything the reader covers, starting from the message header
+    pub fn bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn error(&self, kind: DnsErrorKind) -> DnsError {
+        DnsError { kind, offset: self.pos }
+    }
+
+    pub fn peek_u8(&self) -> Result<u8, DnsError> {
+        match self.data.get(self.pos) {
+            Some(b) => Ok(*b),
+            None => Err(self.error(DnsErrorKind::Truncated)),
+        }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DnsError> {
+        let b = self.peek_u8()?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DnsError> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DnsError> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DnsError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.read_bytes(N)?);
+        Ok(out)
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DnsError> {
+        if self.remaining() < len {
+            return Err(self.error(DnsErrorKind::Truncated));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    // Everything left, used for fields that run to the end of the rdata
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let bytes = &self.data[self.pos.min(self.data.len())..];
+        self.pos = self.data.len();
+        bytes
+    }
+
+    // Splits off a reader over the next `len` bytes and skips past them. The new reader keeps
+    // the earlier part of the message so names in it can still follow pointers backwards
+    pub fn limit(&mut self, len: usize) -> Result<Reader<'a>, DnsError> {
+        if self.remaining() < len {
+            return Err(self.error(DnsErrorKind::Truncated));
+        }
+        let sub = Reader { data: &self.data[..self.pos + len], pos: self.pos };
+        self.pos += len;
+        Ok(sub)
+    }
+}
+
+// Somewhere a message can be written to. A Vec grows as needed while a `Writer` fails once its
+// slice is full, so the same encoder serves both
+pub trait Buffer {
+    // Everything written so far, compression looks back through it for names to point at
+    fn written(&self) -> &[u8];
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), EncodeError>;
+
+    // Overwrites bytes that were already written, used to fill in lengths afterwards
+    fn put_at(&mut self, at: usize, bytes: &[u8]);
+
+    fn put_u8(&mut self, n: u8) -> Result<(), EncodeError> {
+        self.put(&[n])
+    }
+
+    fn put_u16(&mut self, n: u16) -> Result<(), EncodeError> {
+        self.put(&n.to_be_bytes())
+    }
+
+    fn put_u32(&mut self, n: u32) -> Result<(), EncodeError> {
+        self.put(&n.to_be_bytes())
+    }
+}
+
+impl Buffer for Vec<u8> {
+    fn written(&self) -> &[u8] {
+        self
+    }
+
+    // A Vec never runs out of room
+    fn put(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn put_at(&mut self, at: usize, bytes: &[u8]) {
+        self[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+// Writes a byte length followed by the bytes, as for a <character-string>
+pub(crate) fn put_sized(data: &mut impl Buffer, bytes: &[u8]) -> Result<(), EncodeError> {
+    let len = u8::try_from(bytes.len()).map_err(|_| EncodeError::StringTooLong(bytes.len()))?;
+    data.put_u8(len)?;
+    data.put(bytes)
+}
+
+// A 16 bit rdata or option length, anything longer would wrap round and corrupt what follows
+pub(crate) fn rdata_len(len: usize) -> Result<u16, EncodeError> {
+    u16::try_from(len).map_err(|_| EncodeError::RdataTooLong(len))
+}
+
+// Writes into a fixed slice without allocating
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Writer<'a> {
+        Writer { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Buffer for Writer<'_> {
+    fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(EncodeError::BufferFull { needed: end, len: self.buf.len() });
+        }
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn put_at(&mut self, at: usize, bytes: &[u8]) {
+        self.buf[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+}
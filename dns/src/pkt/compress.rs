@@ -0,0 +1,138 @@
+use crate::pkt::{get_deref_ptr, PTR_OFFSET};
+use crate::pkt::codec::Buffer;
+use crate::pkt::error::EncodeError;
+use crate::pkt::name::Name;
+
+// Pointers only have 14 bits, names written past this offset can't be pointed to
+const MAX_PTR: usize = 0x3FFF;
+// How many name suffixes are remembered, later names are still written but never pointed to
+const MAX_NAMES: usize = 128;
+
+// Tracks the names already written to a message so later copies can be replaced
+// with a pointer to the earlier one (RFC 1035 section 4.1.4). Only offsets are kept,
+// candidates are compared against the bytes already written so nothing is allocated
+pub struct Compressor {
+    // Offsets from the start of the message of every name suffix written so far
+    offsets: [u16; MAX_NAMES],
+    count: usize,
+    // Position of the start of the message in the output buffer
+    start: usize,
+    enabled: bool,
+}
+
+impl Compressor {
+    // `data` is the buffer the message is about to be written to
+    pub fn new(data: &impl Buffer) -> Compressor {
+        Compressor {
+            offsets: [0; MAX_NAMES],
+            count: 0,
+            start: data.written().len(),
+            enabled: true,
+        }
+    }
+
+    // Writes every name in full, used for canonical (DNSSEC) encoding and lone records
+    pub fn disabled() -> Compressor {
+        Compressor {
+            offsets: [0; MAX_NAMES],
+            count: 0,
+            start: 0,
+            enabled: false,
+        }
+    }
+
+    pub fn write_name(&mut self, data: &mut impl Buffer, name: &Name) -> Result<(), EncodeError> {
+        if !self.enabled {
+            return write_uncompressed(data, name);
+        }
+        for (i, label) in name.labels().enumerate() {
+            if let Some(offset) = self.find(data.written(), name, i) {
+                return data.put_u16(((PTR_OFFSET as u16) << 8) | offset);
+            }
+            let offset = data.written().len() - self.start;
+            if offset <= MAX_PTR && self.count < MAX_NAMES {
+                self.offsets[self.count] = offset as u16;
+                self.count += 1;
+            }
+            data.put_u8(label.len() as u8)?;
+            data.put(label)?;
+        }
+        data.put_u8(0)
+    }
+
+    // The first remembered name equal to `name` with `skip` labels removed
+    fn find(&self, written: &[u8], name: &Name, skip: usize) -> Option<u16> {
+        let message = &written[self.start..];
+        self.offsets[..self.count].iter().copied()
+            .find(|&offset| wire_eq(message, offset as usize, name.labels().skip(skip)))
+    }
+}
+
+pub fn write_uncompressed(data: &mut impl Buffer, name: &Name) -> Result<(), EncodeError> {
+    for label in name.labels() {
+        data.put_u8(label.len() as u8)?;
+        data.put(label)?;
+    }
+    data.put_u8(0)
+}
+
+// Compares the name at `pos` in a message we wrote ourselves, so its pointers are known good.
+// Labels must match byte for byte, pointing at a name spelled in another case would change how
+// the later name reads (and break 0x20 case randomization)
+fn wire_eq<'a>(message: &[u8], mut pos: usize, mut labels: impl Iterator<Item = &'a [u8]>) -> bool {
+    loop {
+        let size = message[pos];
+        if size & PTR_OFFSET == PTR_OFFSET {
+            pos = get_deref_ptr(u16::from_be_bytes([size, message[pos + 1]]));
+            continue;
+        }
+        let expected = labels.next();
+        if size == 0 {
+            return expected.is_none();
+        }
+        let label = &message[pos + 1..pos + 1 + size as usize];
+        match expected {
+            Some(l) if l == label => pos += 1 + size as usize,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(names: &[&str], compressor: &mut Compressor, data: &mut Vec<u8>) {
+        for name in names {
+            compressor.write_name(data, &Name::from_ascii(name).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn points_at_suffixes() {
+        let mut data = vec![];
+        let mut names = Compressor::new(&data);
+        write(&["example.com", "www.example.com", "example.com"], &mut names, &mut data);
+        assert_eq!(data, [
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+            3, b'w', b'w', b'w', 0xc0, 0,
+            0xc0, 0,
+        ]);
+    }
+
+    #[test]
+    fn keeps_case() {
+        let mut data = vec![];
+        let mut names = Compressor::new(&data);
+        write(&["example.com", "WWW.Example.com"], &mut names, &mut data);
+        // Only `com` is shared byte for byte
+        assert_eq!(data[13..], [3, b'W', b'W', b'W', 7, b'E', b'x', b'a', b'm', b'p', b'l', b'e', 0xc0, 8]);
+    }
+
+    #[test]
+    fn disabled() {
+        let mut data = vec![];
+        write(&["example.com", "example.com"], &mut Compressor::disabled(), &mut data);
+        assert_eq!(data[..13], data[13..]);
+    }
+}
@@ -0,0 +1,269 @@
+use std::cmp::Ordering;
+use data_encoding::BASE32HEX_NOPAD;
+use ring::digest;
+use crate::pkt::answer::Answer;
+use crate::pkt::dnssec::{Nsec, Nsec3, TypeBitmap};
+use crate::pkt::name::Name;
+use crate::pkt::question::Qtype;
+use crate::pkt::rdata::RData;
+
+// NSEC3 chains hashed more often than this are treated as insecure rather than checked (RFC 9276 section 3.2)
+pub const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+// What validated NSEC or NSEC3 records show about a name that has no data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    Secure,
+    // Only shown by an opt-out span or a chain too costly to hash, an unsigned delegation may hide there
+    Insecure,
+}
+
+// Each proof takes the NSEC or NSEC3 records of `zone` that already passed validation, and gives
+// None when they don't prove the claim. NSEC3 is used whenever the zone sent any
+
+// `qname` doesn't exist, and no wildcard could have answered for it
+pub fn nxdomain(zone: &Name, records: &[Answer], qname: &Name) -> Option<Denial> {
+    match Nsec3Chain::new(zone, records) {
+        Some(chain) => chain.nxdomain(qname),
+        None => NsecChain::new(zone, records).nxdomain(qname),
+    }
+}
+
+// `qname` exists but has no `qtype` records, and no CNAME either
+pub fn nodata(zone: &Name, records: &[Answer], qname: &Name, qtype: Qtype) -> Option<Denial> {
+    match Nsec3Chain::new(zone, records) {
+        Some(chain) => chain.nodata(qname, qtype),
+        None => NsecChain::new(zone, records).nodata(qname, qtype),
+    }
+}
+
+// `child` is delegated from `zone` without a DS, so everything below it is unsigned
+pub fn unsigned_delegation(zone: &Name, records: &[Answer], child: &Name) -> Option<Denial> {
+    match Nsec3Chain::new(zone, records) {
+        Some(chain) => chain.unsigned_delegation(child),
+        None => NsecChain::new(zone, records).unsigned_delegation(child),
+    }
+}
+
+// A wildcard with `labels` labels answered for `qname`, so no name between the two may exist
+// (RFC 4035 section 5.3.4)
+pub fn no_closer_match(zone: &Name, records: &[Answer], qname: &Name, labels: u8) -> Option<Denial> {
+    let labels = labels as usize;
+    if labels >= qname.label_count() {
+        return None;
+    }
+    match Nsec3Chain::new(zone, records) {
+        Some(chain) => chain.covering(&qname.suffix(qname.label_count() - labels - 1)).map(|n| chain.opt_out(n)),
+        None => NsecChain::new(zone, records).covering(qname).map(|_| Denial::Secure),
+    }
+}
+
+// Iterated SHA-1 over the canonical owner name and the salt (RFC 5155 section 5)
+pub fn nsec3_hash(name: &Name, salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut data = name.to_lowercase().to_wire();
+    data.extend_from_slice(salt);
+    let mut hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &data);
+    for _ in 0..iterations {
+        data.clear();
+        data.extend_from_slice(hash.as_ref());
+        data.extend_from_slice(salt);
+        hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &data);
+    }
+    hash.as_ref().to_vec()
+}
+
+// The type lists that rule out a name holding `qtype`
+fn lacks(types: &TypeBitmap, qtype: Qtype) -> bool {
+    !types.contains(qtype) && !types.contains(Qtype::CNAME)
+}
+
+// A delegation's records belong to the child, the parent's NSEC there says nothing about them
+fn is_delegation(types: &TypeBitmap) -> bool {
+    types.contains(Qtype::NS) && !types.contains(Qtype::SOA)
+}
+
+struct NsecChain<'a> {
+    zone: &'a Name,
+    links: Vec<(&'a Name, &'a Nsec)>,
+}
+
+impl<'a> NsecChain<'a> {
+    fn new(zone: &'a Name, records: &'a [Answer]) -> NsecChain<'a> {
+        let links = records.iter()
+            .filter(|r| r.name().is_subdomain_of(zone))
+            .filter_map(|r| match r.rdata() {
+                RData::Nsec(nsec) => Some((r.name(), nsec)),
+                _ => None,
+            })
+            .collect();
+        NsecChain { zone, links }
+    }
+
+    fn matching(&self, name: &Name) -> Option<&'a Nsec> {
+        self.links.iter().find(|(owner, _)| *owner == name).map(|(_, nsec)| *nsec)
+    }
+
+    // The NSEC whose span holds `name`, the last one in the zone wraps round to the apex
+    fn covering(&self, name: &Name) -> Option<(&'a Name, &'a Nsec)> {
+        if !name.is_subdomain_of(self.zone) {
+            return None;
+        }
+        self.links.iter().copied().find(|(owner, nsec)| {
+            owner.canonical_cmp(name) == Ordering::Less
+                && (name.canonical_cmp(&nsec.next_name) == Ordering::Less
+                    || nsec.next_name.canonical_cmp(owner) != Ordering::Greater)
+                && !(name.is_subdomain_of(owner) && is_delegation(&nsec.types))
+        })
+    }
+
+    // The closest encloser is the longest ancestor `qname` shares with either end of the span covering it
+    fn closest_encloser(&self, qname: &Name) -> Option<Name> {
+        let (owner, nsec) = self.covering(qname)?;
+        // A span ending below `qname` means it is an empty non-terminal, it exists
+        if nsec.next_name.is_subdomain_of(qname) {
+            return None;
+        }
+        let a = owner.common_ancestor(qname);
+        let b = nsec.next_name.common_ancestor(qname);
+        let encloser = if a.label_count() >= b.label_count() { a } else { b };
+        encloser.is_subdomain_of(self.zone).then_some(encloser)
+    }
+
+    fn nxdomain(&self, qname: &Name) -> Option<Denial> {
+        let encloser = self.closest_encloser(qname)?;
+        self.covering(&encloser.wildcard())?;
+        Some(Denial::Secure)
+    }
+
+    fn nodata(&self, qname: &Name, qtype: Qtype) -> Option<Denial> {
+        if let Some(nsec) = self.matching(qname) {
+            // DS lives on the parent side of a cut, every other type on the child side
+            let wrong_side = match qtype {
+                Qtype::DS => nsec.types.contains(Qtype::SOA) && qname != self.zone,
+                _ => is_delegation(&nsec.types),
+            };
+            return (lacks(&nsec.types, qtype) && !wrong_side).then_some(Denial::Secure);
+        }
+        if let Some((_, nsec)) = self.covering(qname) {
+            if nsec.next_name.is_subdomain_of(qname) {
+                return Some(Denial::Secure);
+            }
+        }
+        // Answered from a wildcard that lacks the type
+        let encloser = self.closest_encloser(qname)?;
+        let wildcard = self.matching(&encloser.wildcard())?;
+        lacks(&wildcard.types, qtype).then_some(Denial::Secure)
+    }
+
+    fn unsigned_delegation(&self, child: &Name) -> Option<Denial> {
+        let nsec = self.matching(child)?;
+        let types = &nsec.types;
+        (types.contains(Qtype::NS) && !types.contains(Qtype::DS) && !types.contains(Qtype::SOA)).then_some(Denial::Secure)
+    }
+}
+
+struct Nsec3Chain<'a> {
+    zone: &'a Name,
+    // Each record with the hash its owner name stands for
+    links: Vec<(Vec<u8>, &'a Nsec3)>,
+}
+
+impl<'a> Nsec3Chain<'a> {
+    // None when the zone sent no usable NSEC3 records
+    fn new(zone: &'a Name, records: &'a [Answer]) -> Option<Nsec3Chain<'a>> {
+        let links: Vec<_> = records.iter()
+            .filter(|r| r.name().label_count() == zone.label_count() + 1 && r.name().is_subdomain_of(zone))
+            .filter_map(|r| match r.rdata() {
+                RData::Nsec3(nsec3) if nsec3.hash_algorithm == 1 => {
+                    let label = r.name().labels().next()?.to_ascii_uppercase();
+                    Some((BASE32HEX_NOPAD.decode(&label).ok()?, nsec3))
+                }
+                _ => None,
+            })
+            .collect();
+        (!links.is_empty()).then_some(Nsec3Chain { zone, links })
+    }
+
+    fn too_costly(&self) -> bool {
+        self.links.iter().any(|(_, n)| n.iterations > MAX_NSEC3_ITERATIONS)
+    }
+
+    fn matching(&self, name: &Name) -> Option<&'a Nsec3> {
+        self.links.iter()
+            .find(|(hash, n)| nsec3_hash(name, &n.salt, n.iterations) == *hash)
+            .map(|(_, n)| *n)
+    }
+
+    // Hashes wrap round at the end of the chain like names do in NSEC
+    fn covering(&self, name: &Name) -> Option<&'a Nsec3> {
+        self.links.iter()
+            .find(|(owner, n)| {
+                let hash = nsec3_hash(name, &n.salt, n.iterations);
+                let next = &n.next_hashed;
+                (*owner < hash && (hash < *next || next <= owner)) || (next <= owner && hash < *next)
+            })
+            .map(|(_, n)| *n)
+    }
+
+    fn opt_out(&self, nsec3: &Nsec3) -> Denial {
+        if nsec3.is_opt_out() { Denial::Insecure } else { Denial::Secure }
+    }
+
+    // The closest encloser proof (RFC 5155 section 8.3): the nearest ancestor that exists and
+    // a covered name one label below it, the next closer name
+    fn closest_encloser(&self, qname: &Name) -> Option<(Name, &'a Nsec3)> {
+        if !qname.is_subdomain_of(self.zone) {
+            return None;
+        }
+        for skip in 1..=qname.label_count() - self.zone.label_count() {
+            let encloser = qname.suffix(skip);
+            if self.matching(&encloser).is_some() {
+                let covering = self.covering(&qname.suffix(skip - 1))?;
+                return Some((encloser, covering));
+            }
+        }
+        None
+    }
+
+    fn nxdomain(&self, qname: &Name) -> Option<Denial> {
+        if self.too_costly() {
+            return Some(Denial::Insecure);
+        }
+        let (encloser, next_closer) = self.closest_encloser(qname)?;
+        self.covering(&encloser.wildcard())?;
+        Some(self.opt_out(next_closer))
+    }
+
+    fn nodata(&self, qname: &Name, qtype: Qtype) -> Option<Denial> {
+        if self.too_costly() {
+            return Some(Denial::Insecure);
+        }
+        if let Some(nsec3) = self.matching(qname) {
+            let wrong_side = match qtype {
+                Qtype::DS => nsec3.types.contains(Qtype::SOA) && qname != self.zone,
+                _ => is_delegation(&nsec3.types),
+            };
+            return (lacks(&nsec3.types, qtype) && !wrong_side).then_some(Denial::Secure);
+        }
+        let (encloser, next_closer) = self.closest_encloser(qname)?;
+        // No DS for a name inside an opt-out span (RFC 5155 section 8.6)
+        if qtype == Qtype::DS && next_closer.is_opt_out() {
+            return Some(Denial::Insecure);
+        }
+        let wildcard = self.matching(&encloser.wildcard())?;
+        lacks(&wildcard.types, qtype).then_some(Denial::Secure)
+    }
+
+    fn unsigned_delegation(&self, child: &Name) -> Option<Denial> {
+        if self.too_costly() {
+            return Some(Denial::Insecure);
+        }
+        if let Some(nsec3) = self.matching(child) {
+            let types = &nsec3.types;
+            return (types.contains(Qtype::NS) && !types.contains(Qtype::DS) && !types.contains(Qtype::SOA))
+                .then_some(Denial::Secure);
+        }
+        let (_, next_closer) = self.closest_encloser(child)?;
+        next_closer.is_opt_out().then_some(Denial::Insecure)
+    }
+}
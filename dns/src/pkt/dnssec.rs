@@ -0,0 +1,687 @@
+use std::fmt;
+use std::fmt::Formatter;
+use data_encoding::{BASE32HEX_NOPAD, BASE64, HEXUPPER};
+use strum_macros::EnumString;
+use crate::pkt::codec::{put_sized, Buffer, Reader};
+use crate::pkt::compress::write_uncompressed;
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+use crate::pkt::name::Name;
+use crate::pkt::parse_name;
+use crate::pkt::question::Qtype;
+use crate::pkt::rdata::take_character_string;
+
+// DNSKEY flag bits (RFC 4034 section 2.1.1, RFC 5011 section 3)
+pub const ZONE_KEY: u16 = 1 << 8;
+pub const REVOKE: u16 = 1 << 7;
+pub const SECURE_ENTRY_POINT: u16 = 1;
+
+// The NSEC3 flag marking unsigned delegations as possibly left out (RFC 5155 section 3.1.2.1)
+pub const OPT_OUT: u8 = 1;
+
+// DNSSEC signing algorithms, the mnemonics are the ones IANA lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    #[strum(ascii_case_insensitive)]
+    RSAMD5,
+    #[strum(ascii_case_insensitive)]
+    DSA,
+    #[strum(ascii_case_insensitive)]
+    RSASHA1,
+    #[strum(serialize = "DSANSEC3SHA1", serialize = "DSA-NSEC3-SHA1", ascii_case_insensitive)]
+    DSANSEC3SHA1,
+    #[strum(serialize = "RSASHA1NSEC3SHA1", serialize = "RSASHA1-NSEC3-SHA1", ascii_case_insensitive)]
+    RSASHA1NSEC3SHA1,
+    #[strum(ascii_case_insensitive)]
+    RSASHA256,
+    #[strum(ascii_case_insensitive)]
+    RSASHA512,
+    #[strum(ascii_case_insensitive)]
+    ECCGOST,
+    #[strum(ascii_case_insensitive)]
+    ECDSAP256SHA256,
+    #[strum(ascii_case_insensitive)]
+    ECDSAP384SHA384,
+    #[strum(ascii_case_insensitive)]
+    ED25519,
+    #[strum(ascii_case_insensitive)]
+    ED448,
+    #[strum(disabled)]
+    Unknown(u8)
+}
+
+impl From<u8> for Algorithm {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Algorithm::RSAMD5,
+            3 => Algorithm::DSA,
+            5 => Algorithm::RSASHA1,
+            6 => Algorithm::DSANSEC3SHA1,
+            7 => Algorithm::RSASHA1NSEC3SHA1,
+            8 => Algorithm::RSASHA256,
+            10 => Algorithm::RSASHA512,
+            12 => Algorithm::ECCGOST,
+            13 => Algorithm::ECDSAP256SHA256,
+            14 => Algorithm::ECDSAP384SHA384,
+            15 => Algorithm::ED25519,
+            16 => Algorithm::ED448,
+            _ => Algorithm::Unknown(value),
+        }
+    }
+}
+
+impl From<Algorithm> for u8 {
+    fn from(value: Algorithm) -> Self {
+        match value {
+            Algorithm::RSAMD5 => 1,
+            Algorithm::DSA => 3,
+            Algorithm::RSASHA1 => 5,
+            Algorithm::DSANSEC3SHA1 => 6,
+            Algorithm::RSASHA1NSEC3SHA1 => 7,
+            Algorithm::RSASHA256 => 8,
+            Algorithm::RSASHA512 => 10,
+            Algorithm::ECCGOST => 12,
+            Algorithm::ECDSAP256SHA256 => 13,
+            Algorithm::ECDSAP384SHA384 => 14,
+            Algorithm::ED25519 => 15,
+            Algorithm::ED448 => 16,
+            Algorithm::Unknown(alg) => alg,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Unknown(alg) => write!(f, "{}", alg),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// The hash a DS record holds of its DNSKEY
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DigestType {
+    #[strum(serialize = "SHA1", serialize = "SHA-1", ascii_case_insensitive)]
+    SHA1,
+    #[strum(serialize = "SHA256", serialize = "SHA-256", ascii_case_insensitive)]
+    SHA256,
+    #[strum(ascii_case_insensitive)]
+    GOST,
+    #[strum(serialize = "SHA384", serialize = "SHA-384", ascii_case_insensitive)]
+    SHA384,
+    #[strum(disabled)]
+    Unknown(u8)
+}
+
+impl From<u8> for DigestType {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => DigestType::SHA1,
+            2 => DigestType::SHA256,
+            3 => DigestType::GOST,
+            4 => DigestType::SHA384,
+            _ => DigestType::Unknown(value),
+        }
+    }
+}
+
+impl From<DigestType> for u8 {
+    fn from(value: DigestType) -> Self {
+        match value {
+            DigestType::SHA1 => 1,
+            DigestType::SHA256 => 2,
+            DigestType::GOST => 3,
+            DigestType::SHA384 => 4,
+            DigestType::Unknown(ty) => ty,
+        }
+    }
+}
+
+impl fmt::Display for DigestType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestType::Unknown(ty) => write!(f, "{}", ty),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// The rdata of DNSKEY and CDNSKEY (RFC 4034 section 2, RFC 7344)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dnskey {
+    pub flags: u16,
+    // Always 3
+    pub protocol: u8,
+    pub algorithm: Algorithm,
+    pub public_key: Vec<u8>,
+}
+
+// The rdata of DS and CDS (RFC 4034 section 5, RFC 7344)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ds {
+    pub key_tag: u16,
+    pub algorithm: Algorithm,
+    pub digest_type: DigestType,
+    pub digest: Vec<u8>,
+}
+
+// RFC 4034 section 3
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rrsig {
+    pub type_covered: Qtype,
+    pub algorithm: Algorithm,
+    // Labels in the signed owner name, not counting the root or a leading `*`
+    pub labels: u8,
+    pub original_ttl: u32,
+    // Seconds since 1970, compared with serial number arithmetic as they wrap in 2106
+    pub expiration: u32,
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer_name: Name,
+    pub signature: Vec<u8>,
+}
+
+// RFC 4034 section 4
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nsec {
+    pub next_name: Name,
+    pub types: TypeBitmap,
+}
+
+// RFC 5155 section 3
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nsec3 {
+    // 1 is SHA-1, the only one defined
+    pub hash_algorithm: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+    // The unencoded hash of the next owner name in hash order
+    pub next_hashed: Vec<u8>,
+    pub types: TypeBitmap,
+}
+
+// RFC 5155 section 4
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nsec3Param {
+    pub hash_algorithm: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+}
+
+// The types present at an NSEC or NSEC3 owner name, kept sorted and without repeats
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeBitmap {
+    types: Vec<u16>,
+}
+
+impl Dnskey {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Dnskey, DnsError> {
+        Ok(Dnskey {
+            flags: rdata.read_u16()?,
+            protocol: rdata.read_u8()?,
+            algorithm: Algorithm::from(rdata.read_u8()?),
+            public_key: rdata.read_rest().to_vec(),
+        })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u16(self.flags)?;
+        data.put_u8(self.protocol)?;
+        data.put_u8(u8::from(self.algorithm))?;
+        data.put(&self.public_key)
+    }
+
+    pub fn is_zone_key(&self) -> bool {
+        self.flags & ZONE_KEY != 0
+    }
+
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags & SECURE_ENTRY_POINT != 0
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.flags & REVOKE != 0
+    }
+
+    // The checksum RRSIG and DS records use to pick out this key (RFC 4034 appendix B)
+    pub fn key_tag(&self) -> u16 {
+        let mut rdata = self.flags.to_be_bytes().to_vec();
+        rdata.extend([self.protocol, u8::from(self.algorithm)]);
+        rdata.extend_from_slice(&self.public_key);
+        // RSA/MD5 keys use bits of the modulus instead
+        if self.algorithm == Algorithm::RSAMD5 {
+            let len = rdata.len();
+            return match len {
+                0..=6 => 0,
+                _ => u16::from_be_bytes([rdata[len - 3], rdata[len - 2]]),
+            };
+        }
+        let mut sum: u32 = 0;
+        for (i, &b) in rdata.iter().enumerate() {
+            sum += if i % 2 == 0 { (b as u32) << 8 } else { b as u32 };
+        }
+        sum += (sum >> 16) & 0xFFFF;
+        sum as u16
+    }
+}
+
+impl Ds {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Ds, DnsError> {
+        Ok(Ds {
+            key_tag: rdata.read_u16()?,
+            algorithm: Algorithm::from(rdata.read_u8()?),
+            digest_type: DigestType::from(rdata.read_u8()?),
+            digest: rdata.read_rest().to_vec(),
+        })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u16(self.key_tag)?;
+        data.put_u8(u8::from(self.algorithm))?;
+        data.put_u8(u8::from(self.digest_type))?;
+        data.put(&self.digest)
+    }
+}
+
+impl Rrsig {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Rrsig, DnsError> {
+        Ok(Rrsig {
+            type_covered: Qtype::deserialize(rdata)?,
+            algorithm: Algorithm::from(rdata.read_u8()?),
+            labels: rdata.read_u8()?,
+            original_ttl: rdata.read_u32()?,
+            expiration: rdata.read_u32()?,
+            inception: rdata.read_u32()?,
+            key_tag: rdata.read_u16()?,
+            signer_name: parse_name(rdata)?,
+            signature: rdata.read_rest().to_vec(),
+        })
+    }
+
+    // The signer name is never compressed (RFC 4034 section 3.1.7)
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        self.type_covered.serialize(data)?;
+        data.put_u8(u8::from(self.algorithm))?;
+        data.put_u8(self.labels)?;
+        data.put_u32(self.original_ttl)?;
+        data.put_u32(self.expiration)?;
+        data.put_u32(self.inception)?;
+        data.put_u16(self.key_tag)?;
+        write_uncompressed(data, &self.signer_name)?;
+        data.put(&self.signature)
+    }
+}
+
+impl Nsec {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Nsec, DnsError> {
+        Ok(Nsec {
+            next_name: parse_name(rdata)?,
+            types: TypeBitmap::deserialize(rdata)?,
+        })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        write_uncompressed(data, &self.next_name)?;
+        self.types.serialize(data)
+    }
+}
+
+impl Nsec3 {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Nsec3, DnsError> {
+        let hash_algorithm = rdata.read_u8()?;
+        let flags = rdata.read_u8()?;
+        let iterations = rdata.read_u16()?;
+        let salt = take_character_string(rdata)?.to_vec();
+        let next_hashed = take_character_string(rdata)?;
+        if next_hashed.is_empty() {
+            return Err(rdata.error(DnsErrorKind::BadRdata));
+        }
+        Ok(Nsec3 {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt,
+            next_hashed: next_hashed.to_vec(),
+            types: TypeBitmap::deserialize(rdata)?,
+        })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u8(self.hash_algorithm)?;
+        data.put_u8(self.flags)?;
+        data.put_u16(self.iterations)?;
+        put_sized(data, &self.salt)?;
+        put_sized(data, &self.next_hashed)?;
+        self.types.serialize(data)
+    }
+
+    pub fn is_opt_out(&self) -> bool {
+        self.flags & OPT_OUT != 0
+    }
+}
+
+impl Nsec3Param {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Nsec3Param, DnsError> {
+        Ok(Nsec3Param {
+            hash_algorithm: rdata.read_u8()?,
+            flags: rdata.read_u8()?,
+            iterations: rdata.read_u16()?,
+            salt: take_character_string(rdata)?.to_vec(),
+        })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u8(self.hash_algorithm)?;
+        data.put_u8(self.flags)?;
+        data.put_u16(self.iterations)?;
+        put_sized(data, &self.salt)
+    }
+}
+
+impl TypeBitmap {
+    pub fn new(types: impl IntoIterator<Item = Qtype>) -> TypeBitmap {
+        let mut types: Vec<u16> = types.into_iter().map(u16::from).collect();
+        types.sort_unstable();
+        types.dedup();
+        TypeBitmap { types }
+    }
+
+    pub fn contains(&self, ty: Qtype) -> bool {
+        self.types.binary_search(&u16::from(ty)).is_ok()
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = Qtype> + '_ {
+        self.types.iter().map(|&ty| Qtype::from(ty))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    // Windows of 256 types, each a window number, a length and up to 32 bytes of bits. Empty
+    // windows, trailing zero bytes and windows out of order are all malformed (RFC 4034 section 4.1.2)
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<TypeBitmap, DnsError> {
+        let mut types = vec![];
+        let mut last: Option<u8> = None;
+        while !rdata.is_empty() {
+            let start = *rdata;
+            let window = rdata.read_u8()?;
+            let len = rdata.read_u8()? as usize;
+            if len == 0 || len > 32 || last.is_some_and(|last| window <= last) {
+                return Err(start.error(DnsErrorKind::BadRdata));
+            }
+            let bits = rdata.read_bytes(len)?;
+            if bits[len - 1] == 0 {
+                return Err(start.error(DnsErrorKind::BadRdata));
+            }
+            for (i, &byte) in bits.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        types.push((window as u16) << 8 | (i * 8 + bit) as u16);
+                    }
+                }
+            }
+            last = Some(window);
+        }
+        Ok(TypeBitmap { types })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        let mut i = 0;
+        while i < self.types.len() {
+            let window = self.types[i] >> 8;
+            let mut bits = [0u8; 32];
+            let mut len = 0;
+            while i < self.types.len() && self.types[i] >> 8 == window {
+                let low = (self.types[i] & 0xFF) as usize;
+                bits[low / 8] |= 0x80 >> (low % 8);
+                len = low / 8 + 1;
+                i += 1;
+            }
+            data.put_u8(window as u8)?;
+            data.put_u8(len as u8)?;
+            data.put(&bits[..len])?;
+        }
+        Ok(())
+    }
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// The inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Reads an RRSIG time, either YYYYMMDDHHmmSS in UTC or plain seconds (RFC 4034 section 3.2)
+pub fn parse_time(s: &str) -> Option<u32> {
+    if !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if s.len() != 14 {
+        return s.parse().ok();
+    }
+    let field = |range: std::ops::Range<usize>| s[range].parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    let days = days_from_civil(year, month, day);
+    // Catches days past the end of the month, which would otherwise roll over
+    if !(1..=12).contains(&month) || civil_from_days(days) != (year, month, day)
+        || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    u32::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+pub(crate) fn fmt_time(f: &mut Formatter<'_>, time: u32) -> fmt::Result {
+    let time = time as i64;
+    let (year, month, day) = civil_from_days(time / 86400);
+    let secs = time % 86400;
+    write!(f, "{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn fmt_salt(f: &mut Formatter<'_>, salt: &[u8]) -> fmt::Result {
+    if salt.is_empty() {
+        return write!(f, "-");
+    }
+    write!(f, "{}", HEXUPPER.encode(salt))
+}
+
+impl fmt::Display for Dnskey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.flags, self.protocol, u8::from(self.algorithm), BASE64.encode(&self.public_key))
+    }
+}
+
+impl fmt::Display for Ds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.key_tag, u8::from(self.algorithm), u8::from(self.digest_type),
+               HEXUPPER.encode(&self.digest))
+    }
+}
+
+impl fmt::Display for Rrsig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {} ", self.type_covered, u8::from(self.algorithm), self.labels, self.original_ttl)?;
+        fmt_time(f, self.expiration)?;
+        write!(f, " ")?;
+        fmt_time(f, self.inception)?;
+        write!(f, " {} {} {}", self.key_tag, self.signer_name, BASE64.encode(&self.signature))
+    }
+}
+
+impl fmt::Display for Nsec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.next_name)?;
+        if !self.types.is_empty() {
+            write!(f, " {}", self.types)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Nsec3 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} ", self.hash_algorithm, self.flags, self.iterations)?;
+        fmt_salt(f, &self.salt)?;
+        write!(f, " {}", BASE32HEX_NOPAD.encode(&self.next_hashed))?;
+        if !self.types.is_empty() {
+            write!(f, " {}", self.types)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Nsec3Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} ", self.hash_algorithm, self.flags, self.iterations)?;
+        fmt_salt(f, &self.salt)
+    }
+}
+
+impl fmt::Display for TypeBitmap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, ty) in self.types().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", ty)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::answer::Answer;
+    use crate::pkt::message::Message;
+    use crate::pkt::rdata::RData;
+
+    // RFC 4034 section 5.4
+    const DSKEY: &str = "dskey.example.com.\t86400\tIN\tDNSKEY\t256 3 5 AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/\
+        2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==";
+
+    fn record(s: &str) -> Answer {
+        s.parse().unwrap()
+    }
+
+    // Presentation form back to itself, then through a message and back
+    fn round_trip(s: &str) {
+        let a = record(s);
+        assert_eq!(a.to_string(), format!("{}\n", s));
+        let mut message = Message::new();
+        message.add_answer(a.clone());
+        let data = message.to_vec().unwrap();
+        assert_eq!(Message::deserialize(&data).unwrap().answers(), [a]);
+    }
+
+    fn bitmap(data: &[u8]) -> Result<TypeBitmap, DnsError> {
+        TypeBitmap::deserialize(&mut Reader::new(data))
+    }
+
+    #[test]
+    fn record_types() {
+        round_trip(DSKEY);
+        round_trip("dskey.example.com.\t86400\tIN\tDS\t60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118");
+        round_trip("host.example.com.\t86400\tIN\tRRSIG\tA 5 3 86400 20030322173103 20030220173103 2642 example.com. \
+            oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTrPYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6oB9wfuh3DTJXUAfI/M0zmO/zz8bW0Rznl8O3tGNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkGJ5D6fwFm8nN+6pBzeDQfsS3Ap3o=");
+        round_trip("alfa.example.com.\t86400\tIN\tNSEC\thost.example.com. A MX RRSIG NSEC TYPE1234");
+        round_trip("0p9mhaveqvm6t7vbl5lop2u3t2rp3tom.example.\t3600\tIN\tNSEC3\t1 1 12 AABBCCDD \
+            2T7B4G4VSA5SMI47K61MV5BV1A22BOJR NS SOA MX RRSIG DNSKEY NSEC3PARAM");
+        round_trip("example.\t0\tIN\tNSEC3PARAM\t1 0 12 AABBCCDD");
+        round_trip("example.\t0\tIN\tNSEC3PARAM\t1 0 0 -");
+    }
+
+    #[test]
+    fn key_tag() {
+        let RData::Dnskey(key) = record(DSKEY).rdata().clone() else { panic!() };
+        assert_eq!(key.key_tag(), 60485);
+        assert!(key.is_zone_key() && !key.is_secure_entry_point() && !key.is_revoked());
+    }
+
+    #[test]
+    fn rrsig_times() {
+        let a = record("host.example.com. 86400 IN RRSIG A 5 3 86400 20030322173103 1045762263 2642 example.com. AA==");
+        let RData::Rrsig(sig) = a.rdata() else { panic!() };
+        assert_eq!((sig.expiration, sig.inception), (1048354263, 1045762263));
+        assert_eq!(parse_time("20230231000000"), None);
+        assert_eq!(parse_time("21060207062815"), Some(u32::MAX));
+        assert_eq!(parse_time("21060207062816"), None);
+    }
+
+    #[test]
+    fn nsec_wire_form() {
+        // RFC 4034 section 4.3
+        let a = record("alfa.example.com. 86400 IN NSEC host.example.com. A MX RRSIG NSEC TYPE1234");
+        let RData::Nsec(nsec) = a.rdata() else { panic!() };
+        let mut data = vec![];
+        nsec.serialize(&mut data).unwrap();
+        let mut expected = b"\x04host\x07example\x03com\x00\x00\x06\x40\x01\x00\x00\x00\x03\x04\x1b".to_vec();
+        expected.extend_from_slice(&[0; 26]);
+        expected.push(0x20);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn malformed_bitmaps() {
+        assert_eq!(bitmap(&[0, 1, 0x40]).unwrap().types().collect::<Vec<_>>(), [Qtype::A]);
+        // Empty window, trailing zero byte, windows out of order, window longer than 32 bytes
+        assert_eq!(bitmap(&[0, 0]).unwrap_err().kind, DnsErrorKind::BadRdata);
+        assert_eq!(bitmap(&[0, 2, 0x40, 0]).unwrap_err().kind, DnsErrorKind::BadRdata);
+        assert_eq!(bitmap(&[1, 1, 0x40, 0, 1, 0x40]).unwrap_err(), DnsError { kind: DnsErrorKind::BadRdata, offset: 3 });
+        assert_eq!(bitmap(&[0, 1, 0x40, 0, 1, 0x40]).unwrap_err().kind, DnsErrorKind::BadRdata);
+        let mut long = vec![0, 33];
+        long.extend_from_slice(&[1; 33]);
+        assert_eq!(bitmap(&long).unwrap_err().kind, DnsErrorKind::BadRdata);
+        assert_eq!(bitmap(&[0, 4, 1]).unwrap_err().kind, DnsErrorKind::Truncated);
+    }
+
+    #[test]
+    fn salt_too_long() {
+        let param = Nsec3Param { hash_algorithm: 1, flags: 0, iterations: 0, salt: vec![0; 256] };
+        assert_eq!(param.serialize(&mut vec![]), Err(EncodeError::StringTooLong(256)));
+        let nsec3 = Nsec3 {
+            hash_algorithm: 1,
+            flags: OPT_OUT,
+            iterations: 0,
+            salt: vec![],
+            next_hashed: vec![0; 20],
+            types: TypeBitmap::new([Qtype::NS]),
+        };
+        assert!(nsec3.is_opt_out());
+        let mut data = vec![];
+        nsec3.serialize(&mut data).unwrap();
+        assert_eq!(Nsec3::deserialize(&mut Reader::new(&data)).unwrap(), nsec3);
+        // An empty next hashed owner is malformed
+        assert_eq!(Nsec3::deserialize(&mut Reader::new(&[1, 0, 0, 0, 0, 0])).unwrap_err().kind, DnsErrorKind::BadRdata);
+    }
+}
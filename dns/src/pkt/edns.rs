@@ -0,0 +1,184 @@
+use std::fmt;
+use std::fmt::Formatter;
+use crate::pkt::answer::Answer;
+use crate::pkt::question::Qtype;
+use crate::pkt::rdata::RData;
+use crate::pkt::codec::{rdata_len, Buffer, Reader};
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+
+// The payload size advertised when none is given, small enough to avoid fragmentation (DNS flag day 2020)
+pub const DEFAULT_UDP_SIZE: u16 = 1232;
+
+const DO_BIT: u16 = 1 << 15;
+
+// The EDNS(0) data carried by the OPT pseudo-record (RFC 6891)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edns {
+    // Largest UDP payload the sender can reassemble
+    pub udp_size: u16,
+    // Upper 8 bits of the 12 bit rcode
+    pub extended_rcode: u8,
+    pub version: u8,
+    // DNSSEC OK (RFC 3225)
+    pub dnssec_ok: bool,
+    // The remaining flag bits, zero unless a later RFC defines them
+    pub z: u16,
+    pub options: Vec<EdnsOption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EdnsOption {
+    pub code: u16,
+    pub data: Vec<u8>,
+}
+
+impl Edns {
+    pub fn new(udp_size: u16) -> Edns {
+        Edns {
+            udp_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            z: 0,
+            options: vec![],
+        }
+    }
+
+    // `record` must be an OPT record, the caller checks its type
+    pub(crate) fn from_record(record: &Answer) -> Result<Edns, DnsErrorKind> {
+        if !record.name().is_root() {
+            return Err(DnsErrorKind::BadOpt);
+        }
+        let options = match record.rdata() {
+            RData::Opt(options) => options.clone(),
+            _ => return Err(DnsErrorKind::BadOpt),
+        };
+        let ttl = record.ttl();
+        let flags = ttl as u16;
+        Ok(Edns {
+            udp_size: u16::from(record.class()),
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: flags & DO_BIT != 0,
+            z: flags & !DO_BIT,
+            options,
+        })
+    }
+
+    // The extended rcode, version and flags share the OPT record's TTL field
+    pub(crate) fn ttl(&self) -> u32 {
+        let flags = (if self.dnssec_ok { DO_BIT } else { 0 }) | (self.z & !DO_BIT);
+        (self.extended_rcode as u32) << 24 | (self.version as u32) << 16 | flags as u32
+    }
+
+    // Writes the OPT record carrying this data, owned by the root with the payload size as its class
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u8(0)?;
+        data.put_u16(u16::from(Qtype::OPT))?;
+        data.put_u16(self.udp_size)?;
+        data.put_u32(self.ttl())?;
+        let rdlength: usize = self.options.iter().map(|o| o.data.len() + 4).sum();
+        data.put_u16(rdata_len(rdlength)?)?;
+        for o in self.options.iter() {
+            o.serialize(data)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Edns {
+    fn default() -> Self {
+        Edns::new(DEFAULT_UDP_SIZE)
+    }
+}
+
+impl EdnsOption {
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<EdnsOption, DnsError> {
+        let code = data.read_u16()?;
+        let len = data.read_u16()?;
+        let data = data.read_bytes(len as usize)?.to_vec();
+        Ok(EdnsOption { code, data })
+    }
+
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u16(self.code)?;
+        data.put_u16(rdata_len(self.data.len())?)?;
+        data.put(&self.data)
+    }
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.code)?;
+        for b in self.data.iter() {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Edns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Version\tUDP\tFlags")?;
+        writeln!(f, "{}\t{}\t{}", self.version, self.udp_size, if self.dnssec_ok { "do" } else { "" })?;
+        for o in self.options.iter() {
+            writeln!(f, "{}", o)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::error::DnsError;
+    use crate::pkt::message::Message;
+
+    fn with_edns(edns: Edns) -> Vec<u8> {
+        let mut message = Message::new();
+        message.set_edns(Some(edns));
+        message.to_vec().unwrap()
+    }
+
+    #[test]
+    fn opt_record() {
+        let edns = Edns {
+            udp_size: 4096,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            z: 0,
+            options: vec![EdnsOption { code: 10, data: vec![1, 2, 3, 4, 5, 6, 7, 8] }],
+        };
+        let data = with_edns(edns.clone());
+        assert_eq!(data[10..12], [0, 1]);
+        assert_eq!(data[12..], [
+            0, 0, 41, 0x10, 0, 1, 0, 0x80, 0, 0, 12,
+            0, 10, 0, 8, 1, 2, 3, 4, 5, 6, 7, 8,
+        ]);
+        let message = Message::deserialize(&data).unwrap();
+        assert_eq!(message.edns(), Some(&edns));
+        // Written back once, as the last additional record
+        assert_eq!(message.to_vec().unwrap(), data);
+    }
+
+    #[test]
+    fn multiple_opt() {
+        let mut data = with_edns(Edns::default());
+        let opt = data[12..].to_vec();
+        data.extend_from_slice(&opt);
+        data[11] = 2;
+        let e = Message::deserialize(&data).err().unwrap();
+        assert_eq!(e, DnsError { kind: DnsErrorKind::MultipleOpt, offset: 23 });
+    }
+
+    #[test]
+    fn opt_not_at_root() {
+        let mut data = with_edns(Edns::default());
+        data.splice(12..13, [1, b'a', 0]);
+        let e = Message::deserialize(&data).err().unwrap();
+        assert_eq!(e, DnsError { kind: DnsErrorKind::BadOpt, offset: 12 });
+    }
+}
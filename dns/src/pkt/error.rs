@@ -0,0 +1,234 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use crate::pkt::dnssec::{Algorithm, DigestType};
+use crate::pkt::tsig::TsigRcode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsErrorKind {
+    // The buffer ended in the middle of a field
+    Truncated,
+    // A compression pointer past the end of the message
+    PointerOutOfRange(usize),
+    // A compression pointer to itself or to data after it
+    ForwardPointer(usize),
+    // A compression pointer back into the labels it was reached from
+    PointerLoop(usize),
+    // A label type other than a length or a pointer
+    ReservedLabelType(u8),
+    // A name longer than 255 bytes on the wire
+    NameTooLong,
+    // A label longer than 63 bytes
+    LabelTooLong,
+    // Two dots in a row, or a name with no labels at all
+    EmptyLabel,
+    // A `\` not followed by a character or three digits below 256
+    BadEscape,
+    // The rdata does not match what its type requires
+    BadRdata,
+    // The header promised more records than the message holds
+    CountMismatch { section: &'static str, expected: u16, found: u16 },
+    // An OPT record that isn't owned by the root
+    BadOpt,
+    // More than one OPT record in the additional section
+    MultipleOpt,
+    // A TSIG record that isn't the last record of the message
+    MisplacedTsig,
+    // Bytes were left over after every section was read
+    TrailingData(usize),
+    // A section was given more records than its 16 bit count can hold
+    TooManyRecords(&'static str),
+    // A `"` with no closing quote
+    UnterminatedQuote,
+    // A record in presentation format ended before this field
+    MissingField(&'static str),
+    // A field of a record in presentation format that couldn't be read
+    BadField(&'static str),
+    // More fields than the record's type takes
+    ExtraField,
+    // A type that is neither a known mnemonic nor TYPEnnn
+    UnknownType,
+    // A Unicode name that UTS #46 processing rejects
+    BadIdn,
+    // A name in a dynamic update that isn't in the zone being updated
+    OutsideZone,
+}
+
+// The error returned to callers, `offset` is the byte in the message where parsing failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsError {
+    pub kind: DnsErrorKind,
+    pub offset: usize,
+}
+
+impl fmt::Display for DnsErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsErrorKind::Truncated => write!(f, "message is truncated"),
+            DnsErrorKind::PointerOutOfRange(ptr) => write!(f, "compression pointer to {} is past the end of the message", ptr),
+            DnsErrorKind::ForwardPointer(ptr) => write!(f, "compression pointer to {} points forward", ptr),
+            DnsErrorKind::PointerLoop(ptr) => write!(f, "compression pointer to {} forms a loop", ptr),
+            DnsErrorKind::ReservedLabelType(ty) => write!(f, "reserved label type {:#04b}", ty),
+            DnsErrorKind::NameTooLong => write!(f, "name is longer than 255 bytes"),
+            DnsErrorKind::LabelTooLong => write!(f, "label is longer than 63 bytes"),
+            DnsErrorKind::EmptyLabel => write!(f, "empty label"),
+            DnsErrorKind::BadEscape => write!(f, "invalid escape"),
+            DnsErrorKind::BadRdata => write!(f, "invalid rdata"),
+            DnsErrorKind::CountMismatch { section, expected, found } => {
+                write!(f, "expected {} {} record(s), found {}", expected, section, found)
+            }
+            DnsErrorKind::BadOpt => write!(f, "invalid OPT record"),
+            DnsErrorKind::MultipleOpt => write!(f, "more than one OPT record"),
+            DnsErrorKind::MisplacedTsig => write!(f, "TSIG record is not the last record"),
+            DnsErrorKind::TrailingData(len) => write!(f, "{} trailing byte(s) after message", len),
+            DnsErrorKind::TooManyRecords(section) => write!(f, "too many {} records", section),
+            DnsErrorKind::UnterminatedQuote => write!(f, "unterminated quoted string"),
+            DnsErrorKind::MissingField(field) => write!(f, "missing {}", field),
+            DnsErrorKind::BadField(field) => write!(f, "invalid {}", field),
+            DnsErrorKind::ExtraField => write!(f, "unexpected field"),
+            DnsErrorKind::UnknownType => write!(f, "unknown type"),
+            DnsErrorKind::BadIdn => write!(f, "invalid internationalized name"),
+            DnsErrorKind::OutsideZone => write!(f, "name is outside the zone"),
+        }
+    }
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+impl Error for DnsError {}
+
+// Returned when a message can't be written, because it doesn't fit in the buffer or holds something
+// the wire format can't represent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    // `needed` is how far the write would have gone, past the end of a buffer of `len` bytes
+    BufferFull { needed: usize, len: usize },
+    // A section holds more records than its 16 bit count can say
+    TooManyRecords(&'static str),
+    // A length prefixed string, such as a TXT string or an NSEC3 salt, longer than 255 bytes
+    StringTooLong(usize),
+    // Rdata, or an EDNS option in it, longer than its 16 bit length field can say
+    RdataTooLong(usize),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::BufferFull { needed, len } => {
+                write!(f, "message needs at least {} bytes but the buffer holds {}", needed, len)
+            }
+            EncodeError::TooManyRecords(section) => write!(f, "too many {} records to encode", section),
+            EncodeError::StringTooLong(len) => write!(f, "string of {} bytes is longer than 255", len),
+            EncodeError::RdataTooLong(len) => write!(f, "rdata of {} bytes is longer than 65535", len),
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+// Why an RRset or a DNSKEY failed DNSSEC validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    // No records, or records that don't share an owner, type and class
+    NotAnRrset,
+    // The RRSIG covers another type, names a signer that can't sign the owner, or counts more
+    // labels than the owner has
+    SignatureMismatch,
+    // None of the keys has the owner, algorithm and key tag the RRSIG names
+    NoMatchingKey,
+    UnsupportedAlgorithm(Algorithm),
+    UnsupportedDigest(DigestType),
+    // A public key that doesn't have the form its algorithm requires
+    BadKey,
+    NotYetValid,
+    Expired,
+    BadSignature,
+    // The DS names a different key, or its digest doesn't match
+    DsMismatch,
+    // A DNSKEY RRset with no signature from a key its DS or trust anchor names
+    NoTrustedKey,
+    // An RRset from a signed zone with no RRSIG that verifies
+    MissingSignature,
+    // A negative or wildcard answer from a signed zone without the NSEC or NSEC3 records proving it
+    MissingDenial,
+    // A record that can't be put in the wire form the digest or signature is made over
+    Encode(EncodeError),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NotAnRrset => write!(f, "records are not a single RRset"),
+            ValidationError::SignatureMismatch => write!(f, "RRSIG does not apply to the RRset"),
+            ValidationError::NoMatchingKey => write!(f, "no DNSKEY matches the RRSIG"),
+            ValidationError::UnsupportedAlgorithm(alg) => write!(f, "unsupported algorithm {}", alg),
+            ValidationError::UnsupportedDigest(ty) => write!(f, "unsupported digest type {}", ty),
+            ValidationError::BadKey => write!(f, "malformed public key"),
+            ValidationError::NotYetValid => write!(f, "signature is not valid yet"),
+            ValidationError::Expired => write!(f, "signature has expired"),
+            ValidationError::BadSignature => write!(f, "signature does not verify"),
+            ValidationError::DsMismatch => write!(f, "DS does not match the DNSKEY"),
+            ValidationError::NoTrustedKey => write!(f, "DNSKEY RRset is not signed by a trusted key"),
+            ValidationError::MissingSignature => write!(f, "RRset is not signed"),
+            ValidationError::MissingDenial => write!(f, "no proof of non-existence"),
+            ValidationError::Encode(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ValidationError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<EncodeError> for ValidationError {
+    fn from(e: EncodeError) -> Self {
+        ValidationError::Encode(e)
+    }
+}
+
+// Why a signed message failed TSIG verification (RFC 8945 section 5.2), whether we found it or
+// the other side reported it in its TSIG record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TsigError {
+    Malformed(DnsError),
+    // No TSIG record where one was needed, or too many unsigned messages in a row of a stream
+    Unsigned,
+    // The key name or algorithm isn't the one we share
+    BadKey,
+    BadSig,
+    // Signed further from our clock than the fudge allows
+    BadTime,
+    // Any other error the other side put in its TSIG record
+    Rejected(TsigRcode),
+}
+
+impl fmt::Display for TsigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TsigError::Malformed(e) => write!(f, "malformed message: {}", e),
+            TsigError::Unsigned => write!(f, "message is not signed"),
+            TsigError::BadKey => write!(f, "unknown TSIG key"),
+            TsigError::BadSig => write!(f, "TSIG signature does not verify"),
+            TsigError::BadTime => write!(f, "TSIG time is outside the fudge"),
+            TsigError::Rejected(rcode) => write!(f, "TSIG rejected with {}", rcode),
+        }
+    }
+}
+
+impl Error for TsigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TsigError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
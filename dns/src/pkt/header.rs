@@ -1,125 +1,210 @@
 use std::fmt;
 use std::fmt::Formatter;
-use crate::pkt::{NBitSlice, Serializable, take_u1, take_u16, take_u3, take_u4};
-use bitvec::prelude::*;
-use nom::IResult;
-use crate::pkt::header::Opcode::{IQuery, Query, Status};
-use crate::pkt::header::Rcode::{FormatError, NameError, NoError, NotImplemented, Refused};
-use strum_macros::{EnumString,Display};
-
-pub(crate) struct Header {
+use crate::pkt::Serializable;
+use crate::pkt::codec::{Buffer, Reader};
+use crate::pkt::error::{DnsError, EncodeError};
+use strum_macros::EnumString;
+
+// Bits of the flags word that follows the id
+const QR: u16 = 1 << 15;
+const OPCODE_SHIFT: u16 = 11;
+const AA: u16 = 1 << 10;
+const TC: u16 = 1 << 9;
+const RD: u16 = 1 << 8;
+const RA: u16 = 1 << 7;
+const Z: u16 = 1 << 6;
+const AD: u16 = 1 << 5;
+const CD: u16 = 1 << 4;
+const NIBBLE: u16 = 0xF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
     pub(crate) id: u16,
-    qr: bool,
-    opcode: Opcode, // u4
-    aa: bool,
-    tc: bool,
+    pub(crate) qr: bool,
+    pub(crate) opcode: Opcode, // u4
+    pub(crate) aa: bool,
+    pub(crate) tc: bool,
     pub(crate) rd: bool,
-    ra: bool,
-    z: u8,
-    rcode: Rcode,
+    pub(crate) ra: bool,
+    // Reserved, must be zero
+    pub(crate) z: bool,
+    // Authentic data and checking disabled (RFC 4035 section 3.2)
+    pub(crate) ad: bool,
+    pub(crate) cd: bool,
+    // Only the low 4 bits, the rest live in the OPT record
+    pub(crate) rcode: Rcode,
     pub(crate) qdcount: u16,
     pub(crate) ancount: u16,
-    nscount: u16,
-    arcount: u16
+    pub(crate) nscount: u16,
+    pub(crate) arcount: u16
+}
+
+// The single bit flags of the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flags {
+    pub qr: bool,
+    pub aa: bool,
+    pub tc: bool,
+    pub rd: bool,
+    pub ra: bool,
+    // Reserved, must be zero
+    pub z: bool,
+    pub ad: bool,
+    pub cd: bool,
 }
 
-#[derive(Debug, EnumString, Display)]
-enum Opcode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Opcode {
     Query,
     IQuery,
-    Status
+    Status,
+    Notify,
+    Update,
+    #[strum(disabled)]
+    Other(u8)
 }
 
-#[derive(Debug, EnumString, Display)]
-enum Rcode {
+// The 12 bit rcode, the low 4 bits are in the header and the rest in the OPT record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rcode {
     NoError,
     FormatError,
     NameError,
     NotImplemented,
-    Refused
+    Refused,
+    YXDomain,
+    YXRRSet,
+    NXRRSet,
+    NotAuth,
+    NotZone,
+    // The EDNS version isn't supported (RFC 6891 section 9). The TSIG errors that share its value
+    // are a `TsigRcode`, they only appear in a TSIG record
+    BadVers,
+    #[strum(disabled)]
+    Other(u16)
 }
 
-impl Serializable for Opcode {
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
-        match self {
-            Opcode::Query => {
-                data.extend(&0u8.view_bits::<Msb0>()[..4]);
-            }
-            Opcode::IQuery => {
-                data.extend(&1u8.view_bits::<Msb0>()[..4]);
-            }
-            Opcode::Status => {
-                data.extend(&2u8.view_bits::<Msb0>()[..4]);
-            }
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Query,
+            1 => Opcode::IQuery,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            _ => Opcode::Other(value),
         }
     }
 }
-impl Opcode {
-    fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Opcode>{
-        let (data, code) = take_u4(data).unwrap();
-        match code {
-            0 => Ok((data, Query)),
-            1 => Ok((data, IQuery)),
-            2 => Ok((data, Status)),
-            _ => {panic!{"Unsupported rcode"}},
+
+impl From<Opcode> for u8 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Query => 0,
+            Opcode::IQuery => 1,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Other(code) => code,
         }
     }
 }
 
-impl Serializable for Rcode {
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Rcode::NoError => {
-                data.extend(&0u8.view_bits::<Msb0>()[..4]);
-            }
-            Rcode::FormatError => {
-                data.extend(&1u8.view_bits::<Msb0>()[..4]);
-            }
-            Rcode::NameError => {
-                data.extend(&2u8.view_bits::<Msb0>()[..4]);
-            }
-            Rcode::NotImplemented => {
-                data.extend(&3u8.view_bits::<Msb0>()[..4]);
-            }
-            Rcode::Refused => {
-                data.extend(&4u8.view_bits::<Msb0>()[..4]);
-            }
+            Opcode::Other(code) => write!(f, "OPCODE{}", code),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
+
 impl Rcode {
-    fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Self> {
-        let (res, code) = take_u4(data).unwrap();
-        match code {
-            0 => Ok((res, NoError)),
-            1 => Ok((res, FormatError)),
-            2 => Ok((res, NameError)),
-            3 => Ok((res, NotImplemented)),
-            4 => Ok((res, Refused)),
-            _ => {panic!{"Unsupported rcode"}},
+    // Puts the header's 4 bits together with the 8 carried by EDNS
+    pub fn from_parts(header: Rcode, extended: u8) -> Rcode {
+        Rcode::from((extended as u16) << 4 | (u16::from(header) & NIBBLE))
+    }
+
+    pub fn extended_bits(&self) -> u8 {
+        (u16::from(*self) >> 4) as u8
+    }
+}
+
+impl From<u16> for Rcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Rcode::NoError,
+            1 => Rcode::FormatError,
+            2 => Rcode::NameError,
+            3 => Rcode::NotImplemented,
+            4 => Rcode::Refused,
+            5 => Rcode::YXDomain,
+            6 => Rcode::YXRRSet,
+            7 => Rcode::NXRRSet,
+            8 => Rcode::NotAuth,
+            9 => Rcode::NotZone,
+            16 => Rcode::BadVers,
+            _ => Rcode::Other(value),
+        }
+    }
+}
+
+impl From<Rcode> for u16 {
+    fn from(value: Rcode) -> Self {
+        match value {
+            Rcode::NoError => 0,
+            Rcode::FormatError => 1,
+            Rcode::NameError => 2,
+            Rcode::NotImplemented => 3,
+            Rcode::Refused => 4,
+            Rcode::YXDomain => 5,
+            Rcode::YXRRSet => 6,
+            Rcode::NXRRSet => 7,
+            Rcode::NotAuth => 8,
+            Rcode::NotZone => 9,
+            Rcode::BadVers => 16,
+            Rcode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for Rcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rcode::Other(code) => write!(f, "RCODE{}", code),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
 
 impl Serializable for Header {
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
-        data.extend_from_bitslice(self.id.view_bits::<Msb0>());
-        data.push(self.qr);
-        self.opcode.serialize(data);
-        data.push(self.aa);
-        data.push(self.tc);
-        data.push(self.rd);
-        data.push(self.ra);
-        data.extend(&self.z.view_bits::<Msb0>()[..3]);
-        self.rcode.serialize(data);
-        data.extend(self.qdcount.view_bits::<Msb0>());
-        data.extend(self.ancount.view_bits::<Msb0>());
-        data.extend(self.nscount.view_bits::<Msb0>());
-        data.extend(self.arcount.view_bits::<Msb0>());
+    fn serialize(&self, data: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.encode(data, [self.qdcount, self.ancount, self.nscount, self.arcount])
     }
 }
 
 impl Header {
+    // `counts` are the four section counts, a message passes the lengths of its sections
+    pub(crate) fn encode(&self, data: &mut impl Buffer, counts: [u16; 4]) -> Result<(), EncodeError> {
+        let mut flags = (u8::from(self.opcode) as u16 & NIBBLE) << OPCODE_SHIFT | (u16::from(self.rcode) & NIBBLE);
+        for (set, bit) in [(self.qr, QR), (self.aa, AA), (self.tc, TC), (self.rd, RD), (self.ra, RA),
+            (self.z, Z), (self.ad, AD), (self.cd, CD)] {
+            if set {
+                flags |= bit;
+            }
+        }
+        data.put_u16(self.id)?;
+        data.put_u16(flags)?;
+        for count in counts {
+            data.put_u16(count)?;
+        }
+        Ok(())
+    }
+
     pub fn new() -> Header {
         Header {
             id: 0,
@@ -129,7 +214,9 @@ impl Header {
             tc: false,
             rd: false,
             ra: false,
-            z: 0,
+            z: false,
+            ad: false,
+            cd: false,
             rcode: Rcode::NoError,
             qdcount: 0,
             ancount: 0,
@@ -137,41 +224,174 @@ impl Header {
             arcount: 0
         }
     }
-    pub fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Header> {
-        let (rem, id) = take_u16(data).unwrap();
-        let (rem, qr) = take_u1(rem).unwrap();
-        let (rem, opcode) = Opcode::deserialize(rem).unwrap();
-        let (rem, aa) = take_u1(rem).unwrap();
-        let (rem, tc) = take_u1(rem).unwrap();
-        let (rem, rd) = take_u1(rem).unwrap();
-        let (rem, ra) = take_u1(rem).unwrap();
-        let (rem, z) = take_u3(rem).unwrap();
-        let (rem, rcode) = Rcode::deserialize(rem).unwrap();
-        let (rem, qdcount) = take_u16(rem).unwrap();
-        let (rem, ancount) = take_u16(rem).unwrap();
-        let (rem, nscount) = take_u16(rem).unwrap();
-        let (rem, arcount) = take_u16(rem).unwrap();
-        Ok((rem, Header {
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<Header, DnsError> {
+        let id = data.read_u16()?;
+        let flags = data.read_u16()?;
+        Ok(Header {
             id,
-            qr,
-            opcode,
-            aa,
-            tc,
-            rd,
-            ra,
-            z,
-            rcode,
-            qdcount,
-            ancount,
-            nscount,
-            arcount
-        }))
+            qr: flags & QR != 0,
+            opcode: Opcode::from((flags >> OPCODE_SHIFT & NIBBLE) as u8),
+            aa: flags & AA != 0,
+            tc: flags & TC != 0,
+            rd: flags & RD != 0,
+            ra: flags & RA != 0,
+            z: flags & Z != 0,
+            ad: flags & AD != 0,
+            cd: flags & CD != 0,
+            rcode: Rcode::from(flags & NIBBLE),
+            qdcount: data.read_u16()?,
+            ancount: data.read_u16()?,
+            nscount: data.read_u16()?,
+            arcount: data.read_u16()?
+        })
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags {
+            qr: self.qr,
+            aa: self.aa,
+            tc: self.tc,
+            rd: self.rd,
+            ra: self.ra,
+            z: self.z,
+            ad: self.ad,
+            cd: self.cd,
+        }
+    }
+
+    pub(crate) fn set_flags(&mut self, flags: Flags) {
+        self.qr = flags.qr;
+        self.aa = flags.aa;
+        self.tc = flags.tc;
+        self.rd = flags.rd;
+        self.ra = flags.ra;
+        self.z = flags.z;
+        self.ad = flags.ad;
+        self.cd = flags.cd;
+    }
+
+    // Only the low 4 bits, `Message::rcode` adds the ones carried by EDNS
+    pub fn rcode(&self) -> Rcode {
+        self.rcode
+    }
+
+    pub fn qdcount(&self) -> u16 {
+        self.qdcount
+    }
+
+    pub fn ancount(&self) -> u16 {
+        self.ancount
+    }
+
+    pub fn nscount(&self) -> u16 {
+        self.nscount
+    }
+
+    pub fn arcount(&self) -> u16 {
+        self.arcount
+    }
+
+    // The message shows the full rcode once EDNS is taken into account
+    pub(crate) fn fmt_with_rcode(&self, f: &mut Formatter<'_>, rcode: Rcode) -> fmt::Result {
+        writeln!(f, "Header")?;
+        writeln!(f, "ID\tType\tResponse\tFlags")?;
+        writeln!(f, "{}\t{}\t{}\t{}", self.id, self.opcode, rcode, self.flags())
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header::new()
+    }
+}
+
+// The set flags in dig's lowercase style
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names = [(self.qr, "qr"), (self.aa, "aa"), (self.tc, "tc"), (self.rd, "rd"), (self.ra, "ra"),
+            (self.z, "z"), (self.ad, "ad"), (self.cd, "cd")];
+        let set: Vec<&str> = names.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+        write!(f, "{}", set.join(" "))
     }
 }
+
 impl fmt::Display for Header {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Header")?;
-        writeln!(f, "ID\tType\tResponse")?;
-        writeln!(f, "{}\t{}\t{}", self.id, self.opcode, self.rcode)
+        self.fmt_with_rcode(f, self.rcode)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_vec(header: &Header) -> Vec<u8> {
+        let mut data = vec![];
+        header.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn flags_word(header: &Header) -> u16 {
+        let data = to_vec(header);
+        u16::from_be_bytes([data[2], data[3]])
+    }
+
+    #[test]
+    fn flag_bits() {
+        let mut header = Header::new();
+        header.ad = true;
+        assert_eq!(flags_word(&header), 0x0020);
+        header.ad = false;
+        header.cd = true;
+        assert_eq!(flags_word(&header), 0x0010);
+        (header.qr, header.aa, header.tc, header.rd, header.ra, header.z, header.ad) = (true, true, true, true, true, true, true);
+        assert_eq!(flags_word(&header), 0x87f0);
+        let data = to_vec(&header);
+        let parsed = Header::deserialize(&mut Reader::new(&data)).unwrap();
+        assert_eq!(to_vec(&parsed), data);
+    }
+
+    #[test]
+    fn opcodes() {
+        for (opcode, value) in [(Opcode::Query, 0), (Opcode::Status, 2), (Opcode::Notify, 4),
+            (Opcode::Update, 5), (Opcode::Other(15), 15)] {
+            assert_eq!(Opcode::from(value), opcode);
+            let mut header = Header::new();
+            header.opcode = opcode;
+            assert_eq!(flags_word(&header), (value as u16) << 11);
+        }
+        assert_eq!(Opcode::Other(3).to_string(), "OPCODE3");
+    }
+
+    #[test]
+    fn rcodes() {
+        for value in 0..=4095 {
+            assert_eq!(u16::from(Rcode::from(value)), value);
+        }
+        assert_eq!(Rcode::from(9), Rcode::NotZone);
+        assert_eq!(Rcode::from(12), Rcode::Other(12));
+        // The TSIG errors above it aren't rcodes
+        assert_eq!(Rcode::from(16), Rcode::BadVers);
+        assert_eq!(Rcode::from(17), Rcode::Other(17));
+        let mut header = Header::new();
+        header.rcode = Rcode::YXRRSet;
+        assert_eq!(flags_word(&header), 6);
+    }
+
+    #[test]
+    fn extended_rcode() {
+        let rcode = Rcode::Other(0xabc);
+        assert_eq!(rcode.extended_bits(), 0xab);
+        assert_eq!(Rcode::from_parts(Rcode::Other(0xc), 0xab), rcode);
+        assert_eq!(Rcode::from_parts(Rcode::NotAuth, 0), Rcode::NotAuth);
+        assert_eq!(Rcode::from_parts(Rcode::NoError, 1), Rcode::BadVers);
+    }
+}
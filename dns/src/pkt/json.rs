@@ -0,0 +1,162 @@
+use serde_json::{json, Map, Value};
+use crate::pkt::answer::Answer;
+use crate::pkt::compress::Compressor;
+use crate::pkt::edns::Edns;
+use crate::pkt::error::EncodeError;
+use crate::pkt::message::Message;
+use crate::pkt::question::{Qclass, Qtype, Question};
+use crate::pkt::rdata::RData;
+
+// The JSON representation of DNS messages from RFC 8427, so output lines up with other tools
+impl Message {
+    pub fn to_rfc8427(&self) -> Result<Value, EncodeError> {
+        let header = &self.header;
+        let mut out = Map::new();
+        out.insert("ID".into(), json!(header.id));
+        out.insert("QR".into(), json!(header.qr));
+        out.insert("Opcode".into(), json!(u8::from(header.opcode)));
+        out.insert("AA".into(), json!(header.aa));
+        out.insert("TC".into(), json!(header.tc));
+        out.insert("RD".into(), json!(header.rd));
+        out.insert("RA".into(), json!(header.ra));
+        out.insert("AD".into(), json!(header.ad));
+        out.insert("CD".into(), json!(header.cd));
+        // Only the header bits, the rest are in the OPT record's TTL
+        out.insert("RCODE".into(), json!(u16::from(header.rcode)));
+        out.insert("QDCOUNT".into(), json!(header.qdcount));
+        out.insert("ANCOUNT".into(), json!(header.ancount));
+        out.insert("NSCOUNT".into(), json!(header.nscount));
+        out.insert("ARCOUNT".into(), json!(header.arcount));
+        // The Q* members describe the first question, any others are listed in full
+        if let Some(q) = self.questions.first() {
+            out.insert("QNAME".into(), json!(q.qname.to_string()));
+            out.insert("QTYPE".into(), json!(u16::from(q.qtype)));
+            out.insert("QTYPEname".into(), json!(q.qtype.to_string()));
+            out.insert("QCLASS".into(), json!(u16::from(q.qclass)));
+            out.insert("QCLASSname".into(), json!(q.qclass.to_string()));
+        }
+        if self.questions.len() > 1 {
+            out.insert("questionRRs".into(), self.questions.iter().map(question_json).collect());
+        }
+        for (key, records) in [("answerRRs", &self.answers), ("authorityRRs", &self.authority)] {
+            if !records.is_empty() {
+                out.insert(key.into(), records.iter().map(record_json).collect::<Result<_, _>>()?);
+            }
+        }
+        let mut additional = self.additional.iter().map(record_json).collect::<Result<Vec<_>, _>>()?;
+        if let Some(edns) = &self.edns {
+            additional.push(opt_json(edns)?);
+        }
+        for tsig in self.tsig.iter() {
+            additional.push(record_json(tsig)?);
+        }
+        if !additional.is_empty() {
+            out.insert("additionalRRs".into(), Value::Array(additional));
+        }
+        Ok(Value::Object(out))
+    }
+}
+
+fn question_json(q: &Question) -> Value {
+    json!({
+        "NAME": q.qname.to_string(),
+        "TYPE": u16::from(q.qtype),
+        "TYPEname": q.qtype.to_string(),
+        "CLASS": u16::from(q.qclass),
+        "CLASSname": q.qclass.to_string(),
+    })
+}
+
+fn record_json(r: &Answer) -> Result<Value, EncodeError> {
+    let mut rdata = vec![];
+    r.rdata().serialize(&mut rdata, &mut Compressor::disabled())?;
+    let mut out = Map::new();
+    out.insert("NAME".into(), json!(r.name().to_string()));
+    out.insert("TYPE".into(), json!(u16::from(r.ty())));
+    out.insert("TYPEname".into(), json!(r.ty().to_string()));
+    out.insert("CLASS".into(), json!(u16::from(r.class())));
+    out.insert("CLASSname".into(), json!(r.class().to_string()));
+    out.insert("TTL".into(), json!(r.ttl()));
+    out.insert("RDLENGTH".into(), json!(rdata.len()));
+    match r.rdata() {
+        // Types without a presentation format only get the hex (RFC 8427 section 2.3)
+        RData::Unknown { .. } | RData::Opt(_) => {
+            out.insert("RDATAHEX".into(), json!(hex(&rdata)));
+        }
+        known => {
+            out.insert(format!("rdata{}", r.ty()), json!(known.to_string()));
+        }
+    }
+    Ok(Value::Object(out))
+}
+
+// The OPT record as it went out on the wire, the payload size is its class
+fn opt_json(edns: &Edns) -> Result<Value, EncodeError> {
+    let mut rdata = vec![];
+    for o in edns.options.iter() {
+        o.serialize(&mut rdata)?;
+    }
+    Ok(json!({
+        "NAME": ".",
+        "TYPE": u16::from(Qtype::OPT),
+        "TYPEname": Qtype::OPT.to_string(),
+        "CLASS": edns.udp_size,
+        "CLASSname": Qclass::from(edns.udp_size).to_string(),
+        "TTL": edns.ttl(),
+        "RDLENGTH": rdata.len(),
+        "RDATAHEX": hex(&rdata),
+    }))
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &[u8] = include_bytes!("../../raw_pkt");
+
+    #[test]
+    fn rfc8427_members() {
+        let message = Message::deserialize(RAW).unwrap();
+        let value = message.to_rfc8427().unwrap();
+        assert_eq!(value["ID"], 1337);
+        assert_eq!(value["QR"], true);
+        assert_eq!(value["RD"], true);
+        assert_eq!(value["AA"], false);
+        assert_eq!(value["ANCOUNT"], 4);
+        assert_eq!(value["QNAME"], "www.northeastern.edu.");
+        assert_eq!(value["QTYPEname"], "A");
+        assert_eq!(value["QCLASS"], 1);
+        let answers = value["answerRRs"].as_array().unwrap();
+        assert_eq!(answers.len(), 4);
+        assert_eq!(answers[0]["TYPEname"], "CNAME");
+        assert_eq!(answers[0]["rdataCNAME"], "northeastern.edu.edgekey.net.");
+        assert_eq!(answers[3]["rdataA"], "23.47.188.104");
+        assert_eq!(answers[3]["RDLENGTH"], 4);
+        assert!(value.get("authorityRRs").is_none());
+    }
+
+    #[test]
+    fn opt_and_unknown_as_hex() {
+        let mut message = Message::new();
+        message.set_edns(Some(Edns::default()));
+        message.add_additional(Answer::new(Question::new().qname, Qclass::IN, 0,
+                                           RData::Unknown { ty: 65280, data: vec![1, 2] }));
+        let value = message.to_rfc8427().unwrap();
+        let additional = value["additionalRRs"].as_array().unwrap();
+        assert_eq!(additional[0]["TYPEname"], "TYPE65280");
+        assert_eq!(additional[0]["RDATAHEX"], "0102");
+        assert_eq!(additional[1]["TYPE"], 41);
+        assert_eq!(additional[1]["CLASS"], 1232);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let message = Message::deserialize(RAW).unwrap();
+        let text = serde_json::to_string(&message).unwrap();
+        assert_eq!(serde_json::from_str::<Message>(&text).unwrap(), message);
+    }
+}
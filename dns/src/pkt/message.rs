@@ -1,87 +1,471 @@
 use std::fmt;
 use std::fmt::{Formatter};
-use bitvec::order::Msb0;
-use bitvec::prelude::BitVec;
 use crate::pkt::answer::Answer;
-use crate::pkt::header::Header;
-use crate::pkt::question::Question;
+use crate::pkt::header::{Flags, Header, Opcode, Rcode};
+use crate::pkt::question::{Qtype, Question};
+use crate::pkt::edns::Edns;
 use crate::pkt::Serializable;
+use crate::pkt::builder::MessageBuilder;
+use crate::pkt::codec::{Buffer, Reader, Writer};
+use crate::pkt::compress::Compressor;
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+use crate::pkt::tsig::TsigSigner;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
-    header: Header,
-    questions: Vec<Question>,
-    answers: Vec<Answer>
+    pub(crate) header: Header,
+    pub(crate) questions: Vec<Question>,
+    pub(crate) answers: Vec<Answer>,
+    pub(crate) authority: Vec<Answer>,
+    pub(crate) additional: Vec<Answer>,
+    // Taken out of the additional section, it is still counted in arcount
+    pub(crate) edns: Option<Edns>,
+    // The TSIG record a received message ended with, counted in arcount like the OPT record
+    pub(crate) tsig: Option<Answer>,
+    // Signs the message as it is serialized, its TSIG record is counted in arcount too
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) signer: Option<TsigSigner>,
 }
 
 impl Serializable for Message {
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
-        self.header.serialize(data);
-        for q in self.questions.iter() {
-            q.serialize(data);
-        }
-        for a in self.answers.iter() {
-            a.serialize(data);
-        }
+    fn serialize(&self, data: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.serialize_with_compression(data, true)
     }
 }
 
 impl Message {
-    pub fn deserialize(data: &[u8]) -> Message {
+    pub fn deserialize(data: &[u8]) -> Result<Message, DnsError> {
+        let mut r = Reader::new(data);
+        let message = Message::parse(&mut r)?;
+        if !r.is_empty() {
+            return Err(r.error(DnsErrorKind::TrailingData(r.remaining())));
+        }
+        Ok(message)
+    }
+
+    fn parse(r: &mut Reader) -> Result<Message, DnsError> {
         let mut message = Message::new();
-        let (mut buf , header) = Header::deserialize((data, 0)).unwrap();
-        message.header = header;
+        message.header = Header::deserialize(r)?;
+
+        let questions = parse_section(r, "question", message.header.qdcount, Question::deserialize)?;
+        let answers = parse_section(r, "answer", message.header.ancount, Answer::deserialize)?;
+        let authority = parse_section(r, "authority", message.header.nscount, Answer::deserialize)?;
+        let mut edns = None;
+        let mut tsig = None;
+        let mut read = 0;
+        let additional = parse_section(r, "additional", message.header.arcount, |r| {
+            let start = *r;
+            let record = Answer::deserialize(r)?;
+            read += 1;
+            if record.ty() == Qtype::TSIG {
+                if read != message.header.arcount {
+                    return Err(start.error(DnsErrorKind::MisplacedTsig));
+                }
+                tsig = Some(record);
+                return Ok(None);
+            }
+            if record.ty() != Qtype::OPT {
+                return Ok(Some(record));
+            }
+            if edns.is_some() {
+                return Err(start.error(DnsErrorKind::MultipleOpt));
+            }
+            match Edns::from_record(&record) {
+                Ok(e) => edns = Some(e),
+                Err(kind) => return Err(start.error(kind)),
+            }
+            Ok(None)
+        })?;
+        message.questions = questions;
+        message.answers = answers;
+        message.authority = authority;
+        message.additional = additional.into_iter().flatten().collect();
+        message.edns = edns;
+        message.tsig = tsig;
+        Ok(message)
+    }
+
+    // Canonical encodings (such as for DNSSEC) need every name written in full, pass `compress: false`.
+    // Nothing is written when the message can't be encoded
+    pub fn serialize_with_compression(&self, data: &mut Vec<u8>, compress: bool) -> Result<(), EncodeError> {
+        let start = data.len();
+        let result = self.encode(data, compress);
+        if result.is_err() {
+            data.truncate(start);
+        }
+        result
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut data = vec![];
+        self.encode(&mut data, true)?;
+        Ok(data)
+    }
 
-        for _ in 0..message.header.qdcount {
-            let (rem, q) = Question::deserialize(buf, data).unwrap();
-            buf = rem;
-            message.questions.push(q);
+    // Writes the message to the front of `buf` and returns its length. When it doesn't fit the
+    // caller can retry with fewer records and the TC bit set
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buf);
+        self.encode(&mut writer, true)?;
+        Ok(writer.position())
+    }
+
+    fn encode(&self, data: &mut impl Buffer, compress: bool) -> Result<(), EncodeError> {
+        let start = data.written().len();
+        let mut names = if compress { Compressor::new(data) } else { Compressor::disabled() };
+        // The counts are taken from the sections rather than the header, so they can't fall out of
+        // step with the records written
+        let signed = self.signer.is_some() || self.tsig.is_some();
+        let counts = [
+            section_count("question", self.questions.len())?,
+            section_count("answer", self.answers.len())?,
+            section_count("authority", self.authority.len())?,
+            section_count("additional", self.additional.len() + self.edns.is_some() as usize + signed as usize)?,
+        ];
+        self.header.encode(data, counts)?;
+        for q in self.questions.iter() {
+            q.serialize_with(data, &mut names)?;
         }
-        for _ in 0..message.header.ancount {
-            let (rem, a) = Answer::deserialize(buf, data).unwrap();
-            buf = rem;
-            message.answers.push(a);
+        for a in self.answers.iter().chain(self.authority.iter()).chain(self.additional.iter()) {
+            a.serialize_with(data, &mut names)?;
         }
-        message
+        if let Some(edns) = &self.edns {
+            edns.serialize(data)?;
+        }
+        if let Some(signer) = &self.signer {
+            // The MAC covers the message as it would be without the TSIG record
+            let arcount = 10 + start;
+            data.put_at(arcount, &(counts[3] - 1).to_be_bytes());
+            let record = signer.sign(&data.written()[start..], self.header.id);
+            data.put_at(arcount, &counts[3].to_be_bytes());
+            record.serialize_with(data, &mut Compressor::disabled())?;
+        } else if let Some(tsig) = &self.tsig {
+            tsig.serialize_with(data, &mut Compressor::disabled())?;
+        }
+        Ok(())
     }
 
     pub fn new() -> Message {
         Message {
             header: Header::new(),
             questions: vec![],
-            answers: vec![]
+            answers: vec![],
+            authority: vec![],
+            additional: vec![],
+            edns: None,
+            tsig: None,
+            signer: None,
+        }
+    }
+
+    pub fn add_answer(&mut self, answer: Answer) {
+        self.header.ancount += 1;
+        self.answers.push(answer);
+    }
+
+    pub fn add_authority(&mut self, record: Answer) {
+        self.header.nscount += 1;
+        self.authority.push(record);
+    }
+
+    pub fn add_additional(&mut self, record: Answer) {
+        self.header.arcount += 1;
+        self.additional.push(record);
+    }
+
+    // Replaces the OPT record, keeping arcount in step
+    pub fn set_edns(&mut self, edns: Option<Edns>) {
+        match (&self.edns, &edns) {
+            (None, Some(_)) => self.header.arcount += 1,
+            (Some(_), None) => self.header.arcount -= 1,
+            _ => {}
+        }
+        self.edns = edns;
+    }
+
+    // The full 12 bit rcode, the upper 8 bits come from EDNS when there is an OPT record
+    pub fn rcode(&self) -> Rcode {
+        let extended = self.edns.as_ref().map_or(0, |e| e.extended_rcode);
+        Rcode::from_parts(self.header.rcode, extended)
+    }
+
+    // Rcodes above 15 need somewhere to put their upper bits, so an OPT record is added if missing
+    pub fn set_rcode(&mut self, rcode: Rcode) {
+        self.header.rcode = Rcode::from(u16::from(rcode) & 0xF);
+        let extended = rcode.extended_bits();
+        if extended != 0 && self.edns.is_none() {
+            self.set_edns(Some(Edns::default()));
+        }
+        if let Some(edns) = self.edns.as_mut() {
+            edns.extended_rcode = extended;
         }
     }
 
-    pub fn build(id: u16, url: &str, ty: &str) -> Message {
-        let mut header = Header::new();
-        header.id = id;
-        header.rd = true;
-        header.qdcount = 1;
+    // Drops every record and sets TC so the client retries over TCP (RFC 2181 section 9),
+    // the questions, the OPT record and any TSIG signature stay
+    pub fn truncate(&mut self) {
+        self.answers.clear();
+        self.authority.clear();
+        self.additional.clear();
+        self.header.ancount = 0;
+        self.header.nscount = 0;
+        self.header.arcount = self.edns.is_some() as u16 + (self.tsig.is_some() || self.signer.is_some()) as u16;
+        self.header.tc = true;
+    }
+
+    // Signs the message with `signer` whenever it is serialized, in place of any TSIG record it
+    // was received with. None leaves it unsigned
+    pub fn set_tsig(&mut self, signer: Option<TsigSigner>) {
+        match (self.tsig.is_some() || self.signer.is_some(), signer.is_some()) {
+            (false, true) => self.header.arcount += 1,
+            (true, false) => self.header.arcount -= 1,
+            _ => {}
+        }
+        self.tsig = None;
+        self.signer = signer;
+    }
+
+    // The TSIG record the message was received with, see `TsigVerifier` to check it
+    pub fn tsig(&self) -> Option<&Answer> {
+        self.tsig.as_ref()
+    }
+
+    pub fn edns(&self) -> Option<&Edns> {
+        self.edns.as_ref()
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn id(&self) -> u16 {
+        self.header.id
+    }
+
+    pub fn set_id(&mut self, id: u16) {
+        self.header.id = id;
+    }
 
-        let mut question = Question::new();
-        question.qname = url.parse().unwrap();
-        question.qtype = ty.parse().unwrap();
-        question.qclass = "IN".parse().unwrap();
+    pub fn opcode(&self) -> Opcode {
+        self.header.opcode
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.header.flags()
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.header.set_flags(flags);
+    }
+
+    pub fn questions(&self) -> &[Question] {
+        &self.questions
+    }
 
+    pub fn answers(&self) -> &[Answer] {
+        &self.answers
+    }
+
+    pub fn authority(&self) -> &[Answer] {
+        &self.authority
+    }
+
+    // The OPT record isn't in here, see `edns`
+    pub fn additional(&self) -> &[Answer] {
+        &self.additional
+    }
+
+    // Records of type `ty` from the answer, authority and additional sections in that order
+    pub fn records_of_type(&self, ty: Qtype) -> impl Iterator<Item = &Answer> {
+        self.answers.iter().chain(self.authority.iter()).chain(self.additional.iter())
+            .filter(move |r| r.ty() == ty)
+    }
+
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::new()
+    }
+
+    // A reply with the query's id, opcode, RD and CD bits and questions, and QR set. A query
+    // with EDNS gets EDNS back (RFC 6891 section 7) with its DO bit echoed (RFC 3225 section 3)
+    pub fn response_to(query: &Message) -> Message {
         let mut message = Message::new();
-        message.header = header;
-        message.questions.push(question);
+        message.header.id = query.header.id;
+        message.header.qr = true;
+        message.header.opcode = query.header.opcode;
+        message.header.rd = query.header.rd;
+        message.header.cd = query.header.cd;
+        message.header.qdcount = query.header.qdcount;
+        message.questions = query.questions.clone();
+        if let Some(edns) = &query.edns {
+            message.set_edns(Some(Edns { dnssec_ok: edns.dnssec_ok, ..Edns::default() }));
+        }
         message
     }
 }
 
+// Reads `count` records of one section, running out of input between records is a count mismatch
+fn parse_section<'a, T>(r: &mut Reader<'a>, section: &'static str, count: u16,
+                        mut parse: impl FnMut(&mut Reader<'a>) -> Result<T, DnsError>) -> Result<Vec<T>, DnsError> {
+    let mut records = vec![];
+    for found in 0..count {
+        if r.is_empty() {
+            return Err(r.error(DnsErrorKind::CountMismatch { section, expected: count, found }));
+        }
+        records.push(parse(r)?);
+    }
+    Ok(records)
+}
+
+fn section_count(section: &'static str, len: usize) -> Result<u16, EncodeError> {
+    u16::try_from(len).map_err(|_| EncodeError::TooManyRecords(section))
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Message::new()
+    }
+}
+
+// `{:#}` shows owner names in Unicode. UPDATE messages get their sections' RFC 2136 names
 impl fmt::Display for Message {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.header)?;
-        writeln!(f, "Question(s)")?;
+        let headings = match self.header.opcode {
+            Opcode::Update => ["Zone", "Prerequisite(s)", "Update(s)"],
+            _ => ["Question(s)", "Answer(s)", "Authority"],
+        };
+        self.header.fmt_with_rcode(f, self.rcode())?;
+        writeln!(f, "{}", headings[0])?;
         for q in self.questions.iter() {
-            write!(f, "{}", q)?;
+            fmt_entry(f, q)?;
         }
 
-        writeln!(f, "Answer(s)")?;
+        writeln!(f, "{}", headings[1])?;
         for a in self.answers.iter() {
-            write!(f, "{}", a)?;
+            fmt_entry(f, a)?;
+        }
+
+        writeln!(f, "{}", headings[2])?;
+        for a in self.authority.iter() {
+            fmt_entry(f, a)?;
+        }
+
+        writeln!(f, "Additional")?;
+        for a in self.additional.iter().chain(self.tsig.iter()) {
+            fmt_entry(f, a)?;
+        }
+
+        if let Some(edns) = &self.edns {
+            writeln!(f, "EDNS")?;
+            write!(f, "{}", edns)?;
         }
         write!(f, "")
     }
 }
+
+fn fmt_entry(f: &mut Formatter<'_>, entry: &impl fmt::Display) -> fmt::Result {
+    if f.alternate() {
+        return write!(f, "{:#}", entry);
+    }
+    write!(f, "{}", entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::tsig::{TsigAlgorithm, TsigKey};
+
+    const RAW: &[u8] = include_bytes!("../../raw_pkt");
+
+    #[test]
+    fn counts_follow_sections() {
+        let mut message = Message::deserialize(RAW).unwrap();
+        message.header.qdcount = 7;
+        message.header.arcount = 3;
+        let data = message.to_vec().unwrap();
+        assert_eq!(data[4..12], [0, 1, 0, 4, 0, 0, 0, 0]);
+        assert_eq!(Message::deserialize(&data).unwrap().to_string(), Message::deserialize(RAW).unwrap().to_string());
+    }
+
+    #[test]
+    fn signer_without_arcount() {
+        let key = TsigKey::new("key.", TsigAlgorithm::HmacSha256, b"secret").unwrap();
+        let mut message = Message::new();
+        message.signer = Some(TsigSigner::new(key));
+        let mut data = vec![];
+        message.serialize(&mut data).unwrap();
+        assert_eq!(data[10..12], [0, 1]);
+        assert!(Message::deserialize(&data).unwrap().tsig().is_some());
+    }
+
+    #[test]
+    fn too_many_records() {
+        let mut message = Message::new();
+        message.questions = (0..=u16::MAX as usize).map(|_| Question::new()).collect();
+        let mut buf = [0u8; 512];
+        assert_eq!(message.encode_into(&mut buf), Err(EncodeError::TooManyRecords("question")));
+    }
+
+    #[test]
+    fn truncated_header() {
+        let e = Message::deserialize(&RAW[..7]).err().unwrap();
+        assert_eq!(e, DnsError { kind: DnsErrorKind::Truncated, offset: 6 });
+    }
+
+    #[test]
+    fn truncated_record() {
+        let e = Message::deserialize(&RAW[..RAW.len() - 2]).err().unwrap();
+        assert_eq!(e.kind, DnsErrorKind::Truncated);
+    }
+
+    #[test]
+    fn count_mismatch() {
+        let mut data = RAW.to_vec();
+        data[7] = 5;
+        let e = Message::deserialize(&data).err().unwrap();
+        assert_eq!(e, DnsError {
+            kind: DnsErrorKind::CountMismatch { section: "answer", expected: 5, found: 4 },
+            offset: RAW.len(),
+        });
+    }
+
+    #[test]
+    fn trailing_data() {
+        let mut data = RAW.to_vec();
+        data.extend_from_slice(&[0, 0, 0]);
+        let e = Message::deserialize(&data).err().unwrap();
+        assert_eq!(e, DnsError { kind: DnsErrorKind::TrailingData(3), offset: RAW.len() });
+    }
+
+    #[test]
+    fn encode_into_buffer() {
+        let message = Message::deserialize(RAW).unwrap();
+        let mut buf = [0u8; 512];
+        let len = message.encode_into(&mut buf).unwrap();
+        assert_eq!(buf[..len], *RAW);
+        let mut small = [0u8; 100];
+        assert!(matches!(message.encode_into(&mut small), Err(EncodeError::BufferFull { needed, len: 100 }) if needed > 100));
+    }
+
+    #[test]
+    fn truncate_keeps_questions_and_edns() {
+        let mut message = Message::deserialize(RAW).unwrap();
+        message.set_edns(Some(Edns::default()));
+        message.truncate();
+        let parsed = Message::deserialize(&message.to_vec().unwrap()).unwrap();
+        assert!(parsed.header.tc);
+        assert_eq!(parsed.questions.len(), message.questions.len());
+        assert!(parsed.answers.is_empty());
+        assert!(parsed.edns().is_some());
+    }
+
+    #[test]
+    fn badvers() {
+        let mut message = Message::new();
+        message.set_rcode(Rcode::BadVers);
+        let parsed = Message::deserialize(&message.to_vec().unwrap()).unwrap();
+        assert_eq!(parsed.header().rcode(), Rcode::NoError);
+        assert_eq!(parsed.edns().unwrap().extended_rcode, 1);
+        assert_eq!(parsed.rcode(), Rcode::BadVers);
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt;
+use std::fmt::Formatter;
+use crate::pkt::{get_deref_ptr, skip_name, walk_name, PTR_OFFSET};
+use crate::pkt::answer::{read_rdata, Answer};
+use crate::pkt::codec::Reader;
+use crate::pkt::error::{DnsError, DnsErrorKind};
+use crate::pkt::header::{Header, Opcode};
+use crate::pkt::message::Message;
+use crate::pkt::name::{fmt_label, Name, MAX_NAME_LEN};
+use crate::pkt::question::{Qclass, Qtype, Question};
+use crate::pkt::rdata::RData;
+
+// A message borrowed from the buffer it was received in. Only the header and the section
+// boundaries are read up front, records are decoded as they are iterated and names are
+// never copied, see `Message` for the owned form
+pub struct MessageRef<'a> {
+    data: &'a [u8],
+    header: Header,
+    // Offsets of the question, answer, authority and additional sections
+    sections: [usize; 4],
+}
+
+// A name inside the message, its labels are read from the buffer when asked for
+#[derive(Clone, Copy)]
+pub struct NameRef<'a> {
+    data: &'a [u8],
+    start: usize,
+}
+
+pub struct Labels<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct QuestionRef<'a> {
+    qname: NameRef<'a>,
+    qtype: Qtype,
+    qclass: Qclass,
+}
+
+#[derive(Clone, Copy)]
+pub struct AnswerRef<'a> {
+    name: NameRef<'a>,
+    ty: Qtype,
+    class: Qclass,
+    ttl: u32,
+    // Covers the message up to the end of the rdata, positioned at its start
+    rdata: Reader<'a>,
+}
+
+// Walks `count` entries of one section, stopping at the first error
+pub struct Section<'a, T> {
+    data: Reader<'a>,
+    count: u16,
+    parse: fn(&mut Reader<'a>) -> Result<T, DnsError>,
+}
+
+impl<'a> MessageRef<'a> {
+    pub fn new(data: &'a [u8]) -> Result<MessageRef<'a>, DnsError> {
+        let mut r = Reader::new(data);
+        let header = Header::deserialize(&mut r)?;
+        let mut sections = [0; 4];
+        let counts = [("question", header.qdcount), ("answer", header.ancount),
+            ("authority", header.nscount), ("additional", header.arcount)];
+        for (i, (section, count)) in counts.into_iter().enumerate() {
+            sections[i] = r.position();
+            for found in 0..count {
+                if r.is_empty() {
+                    return Err(r.error(DnsErrorKind::CountMismatch { section, expected: count, found }));
+                }
+                skip_name(&mut r)?;
+                if i == 0 {
+                    r.read_bytes(4)?;
+                } else {
+                    r.read_bytes(8)?;
+                    let rdlength = r.read_u16()?;
+                    r.read_bytes(rdlength as usize)?;
+                }
+            }
+        }
+        if !r.is_empty() {
+            return Err(r.error(DnsErrorKind::TrailingData(r.remaining())));
+        }
+        Ok(MessageRef { data, header, sections })
+    }
+
+    pub fn id(&self) -> u16 {
+        self.header.id
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.header.opcode
+    }
+
+    pub fn questions(&self) -> Section<'a, QuestionRef<'a>> {
+        self.section(0, self.header.qdcount, QuestionRef::parse)
+    }
+
+    pub fn answers(&self) -> Section<'a, AnswerRef<'a>> {
+        self.section(1, self.header.ancount, AnswerRef::parse)
+    }
+
+    pub fn authority(&self) -> Section<'a, AnswerRef<'a>> {
+        self.section(2, self.header.nscount, AnswerRef::parse)
+    }
+
+    // The OPT record is left in here, `to_message` moves it out
+    pub fn additional(&self) -> Section<'a, AnswerRef<'a>> {
+        self.section(3, self.header.arcount, AnswerRef::parse)
+    }
+
+    pub fn to_message(&self) -> Result<Message, DnsError> {
+        Message::deserialize(self.data)
+    }
+
+    fn section<T>(&self, i: usize, count: u16, parse: fn(&mut Reader<'a>) -> Result<T, DnsError>) -> Section<'a, T> {
+        let mut data = Reader::new(self.data);
+        data.seek(self.sections[i]);
+        Section { data, count, parse }
+    }
+}
+
+impl<'a, T> Iterator for Section<'a, T> {
+    type Item = Result<T, DnsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+        self.count -= 1;
+        let item = (self.parse)(&mut self.data);
+        if item.is_err() {
+            self.count = 0;
+        }
+        Some(item)
+    }
+}
+
+impl<'a> NameRef<'a> {
+    // Checks the pointers and length once so the labels can be walked freely afterwards
+    pub(crate) fn parse(data: &mut Reader<'a>) -> Result<NameRef<'a>, DnsError> {
+        let start = data.position();
+        let mut len = 1;
+        walk_name(data, |label, at| {
+            len += label.len() + 1;
+            if len > MAX_NAME_LEN {
+                return Err(DnsError { kind: DnsErrorKind::NameTooLong, offset: at });
+            }
+            Ok(())
+        })?;
+        Ok(NameRef { data: data.bytes(), start })
+    }
+
+    pub fn labels(&self) -> Labels<'a> {
+        Labels { data: self.data, pos: self.start }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    pub fn to_name(&self) -> Name {
+        let mut name = Name::root();
+        for label in self.labels() {
+            // Already checked in `parse`
+            let _ = name.push_label(label.to_vec(), 0);
+        }
+        name
+    }
+}
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let size = self.data[self.pos];
+            if size & PTR_OFFSET == PTR_OFFSET {
+                self.pos = get_deref_ptr(u16::from_be_bytes([size, self.data[self.pos + 1]]));
+                continue;
+            }
+            if size == 0 {
+                return None;
+            }
+            let start = self.pos + 1;
+            self.pos = start + size as usize;
+            return Some(&self.data[start..self.pos]);
+        }
+    }
+}
+
+impl PartialEq<Name> for NameRef<'_> {
+    fn eq(&self, other: &Name) -> bool {
+        let mut labels = self.labels();
+        other.labels().all(|l| labels.next().is_some_and(|m| m.eq_ignore_ascii_case(l)))
+            && labels.next().is_none()
+    }
+}
+
+impl fmt::Display for NameRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return write!(f, ".");
+        }
+        for label in self.labels() {
+            fmt_label(f, label)?;
+            write!(f, ".")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> QuestionRef<'a> {
+    fn parse(data: &mut Reader<'a>) -> Result<QuestionRef<'a>, DnsError> {
+        Ok(QuestionRef {
+            qname: NameRef::parse(data)?,
+            qtype: Qtype::deserialize(data)?,
+            qclass: Qclass::deserialize(data)?,
+        })
+    }
+
+    pub fn qname(&self) -> NameRef<'a> {
+        self.qname
+    }
+
+    pub fn qtype(&self) -> Qtype {
+        self.qtype
+    }
+
+    pub fn qclass(&self) -> Qclass {
+        self.qclass
+    }
+
+    pub fn to_question(&self) -> Question {
+        Question {
+            qname: self.qname.to_name(),
+            qtype: self.qtype,
+            qclass: self.qclass,
+        }
+    }
+}
+
+impl<'a> AnswerRef<'a> {
+    fn parse(data: &mut Reader<'a>) -> Result<AnswerRef<'a>, DnsError> {
+        let name = NameRef::parse(data)?;
+        let ty = Qtype::deserialize(data)?;
+        let class = Qclass::deserialize(data)?;
+        let ttl = data.read_u32()?;
+        let rdlength = data.read_u16()?;
+        let rdata = data.limit(rdlength as usize)?;
+        Ok(AnswerRef { name, ty, class, ttl, rdata })
+    }
+
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+
+    pub fn ty(&self) -> Qtype {
+        self.ty
+    }
+
+    pub fn class(&self) -> Qclass {
+        self.class
+    }
+
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    // The rdata as it appears on the wire, names in it may still be compressed
+    pub fn raw_rdata(&self) -> &'a [u8] {
+        let mut rdata = self.rdata;
+        rdata.read_rest()
+    }
+
+    pub fn rdata(&self) -> Result<RData, DnsError> {
+        let mut rdata = self.rdata;
+        read_rdata(self.ty, self.class, &mut rdata)
+    }
+
+    pub fn to_answer(&self) -> Result<Answer, DnsError> {
+        Ok(Answer::new(self.name.to_name(), self.class, self.ttl, self.rdata()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &[u8] = include_bytes!("../../raw_pkt");
+
+    #[test]
+    fn matches_owned_parse() {
+        let view = MessageRef::new(RAW).unwrap();
+        let message = Message::deserialize(RAW).unwrap();
+        assert_eq!(view.id(), u16::from_be_bytes([RAW[0], RAW[1]]));
+        let text = message.to_string();
+        let questions: String = view.questions().map(|q| q.unwrap().to_question().to_string()).collect();
+        assert!(text.contains(&format!("Question(s)\n{}Answer(s)\n", questions)));
+        let answers: String = view.answers().map(|a| a.unwrap().to_answer().unwrap().to_string()).collect();
+        assert!(text.contains(&format!("Answer(s)\n{}Authority\n", answers)));
+        assert_eq!(view.authority().count(), 0);
+        assert_eq!(view.to_message().unwrap().to_vec().unwrap(), message.to_vec().unwrap());
+    }
+
+    #[test]
+    fn labels_follow_pointers() {
+        let view = MessageRef::new(RAW).unwrap();
+        let last = view.answers().last().unwrap().unwrap();
+        let labels: Vec<&[u8]> = last.name().labels().collect();
+        assert_eq!(labels, [&b"e12215"[..], b"dscb", b"akamaiedge", b"net"]);
+        assert!(last.name() == Name::from_ascii("E12215.dscb.akamaiedge.net.").unwrap());
+        assert_eq!(last.name().to_string(), "e12215.dscb.akamaiedge.net.");
+        // The second CNAME's target ends in a pointer that is left as it is
+        let cname = view.answers().nth(1).unwrap().unwrap();
+        assert_eq!(cname.raw_rdata()[cname.raw_rdata().len() - 2..], [0xc0, 0x4b]);
+    }
+
+    #[test]
+    fn bad_name_found_lazily() {
+        let mut data = RAW.to_vec();
+        // Point the first answer's owner at itself
+        data[0x27] = 0x26;
+        let view = MessageRef::new(&data).unwrap();
+        let mut answers = view.answers();
+        let e = answers.next().unwrap().err().unwrap();
+        assert_eq!(e.kind, DnsErrorKind::ForwardPointer(0x26));
+        assert!(answers.next().is_none());
+    }
+
+    #[test]
+    fn framing_checked_up_front() {
+        assert_eq!(MessageRef::new(&RAW[..RAW.len() - 1]).err().unwrap().kind, DnsErrorKind::Truncated);
+        let mut data = RAW.to_vec();
+        data.push(0);
+        assert_eq!(MessageRef::new(&data).err().unwrap().kind, DnsErrorKind::TrailingData(1));
+    }
+}
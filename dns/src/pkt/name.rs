@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use crate::pkt::error::{DnsError, DnsErrorKind};
+
+// Longest a single label may be (RFC 1035 section 2.3.4)
+pub const MAX_LABEL_LEN: usize = 63;
+// Longest a name may be on the wire, length bytes and the root label included
+pub const MAX_NAME_LEN: usize = 255;
+
+// A domain name as its raw labels, the root label is implied and never stored.
+// Labels may hold any byte, comparisons ignore ASCII case (RFC 4343)
+#[derive(Debug, Clone, Default)]
+pub struct Name {
+    labels: Vec<Vec<u8>>,
+}
+
+impl Name {
+    pub fn root() -> Name {
+        Name { labels: vec![] }
+    }
+
+    // Parses the RFC 1035 presentation format, `\.` and `\DDD` escapes included.
+    // The trailing dot is optional, every name is taken to be fully qualified
+    pub fn from_ascii(s: &str) -> Result<Name, DnsError> {
+        let err = |kind, offset| Err(DnsError { kind, offset });
+        let mut name = Name::root();
+        if s == "." {
+            return Ok(name);
+        }
+        let bytes = s.as_bytes();
+        let mut label = vec![];
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'.' => {
+                    if label.is_empty() {
+                        return err(DnsErrorKind::EmptyLabel, i);
+                    }
+                    name.push_label(label, i)?;
+                    label = vec![];
+                }
+                b'\\' => {
+                    let digits = &bytes[i + 1..bytes.len().min(i + 4)];
+                    if digits.len() == 3 && digits.iter().all(u8::is_ascii_digit) {
+                        let value = digits.iter().fold(0u32, |acc, d| acc * 10 + (d - b'0') as u32);
+                        if value > 255 {
+                            return err(DnsErrorKind::BadEscape, i);
+                        }
+                        label.push(value as u8);
+                        i += 3;
+                    } else if let Some(&c) = digits.first() {
+                        if c.is_ascii_digit() {
+                            return err(DnsErrorKind::BadEscape, i);
+                        }
+                        label.push(c);
+                        i += 1;
+                    } else {
+                        return err(DnsErrorKind::BadEscape, i);
+                    }
+                }
+                c => label.push(c),
+            }
+            i += 1;
+        }
+        if !label.is_empty() {
+            name.push_label(label, bytes.len())?;
+        } else if bytes.is_empty() {
+            return err(DnsErrorKind::EmptyLabel, 0);
+        }
+        Ok(name)
+    }
+
+    // Converts a name that may hold Unicode labels to A-labels with UTS #46 processing, so
+    // `bücher.de` becomes `xn--bcher-kva.de`. ASCII input is read as by `from_ascii`
+    pub fn from_unicode(s: &str) -> Result<Name, DnsError> {
+        if s.is_ascii() {
+            return Name::from_ascii(s);
+        }
+        match idna::domain_to_ascii(s) {
+            Ok(ascii) => Name::from_ascii(&ascii),
+            Err(_) => Err(DnsError { kind: DnsErrorKind::BadIdn, offset: 0 }),
+        }
+    }
+
+    // The presentation format with A-labels shown as Unicode, the same as `{:#}`
+    pub fn to_unicode(&self) -> String {
+        format!("{:#}", self)
+    }
+
+    // Like `from_ascii`, but a name without a trailing dot is taken relative to `origin` and
+    // `@` stands for the origin itself (RFC 1035 section 5.1)
+    pub fn from_ascii_in(s: &str, origin: &Name) -> Result<Name, DnsError> {
+        if s == "@" {
+            return Ok(origin.clone());
+        }
+        let mut name = Name::from_ascii(s)?;
+        if !is_absolute(s) {
+            for label in origin.labels.iter() {
+                name.push_label(label.clone(), s.len())?;
+            }
+        }
+        Ok(name)
+    }
+
+    // Adds `label` below the labels already read, `offset` is reported on errors
+    pub(crate) fn push_label(&mut self, label: Vec<u8>, offset: usize) -> Result<(), DnsError> {
+        if label.len() > MAX_LABEL_LEN {
+            return Err(DnsError { kind: DnsErrorKind::LabelTooLong, offset });
+        }
+        if self.wire_len() + label.len() + 1 > MAX_NAME_LEN {
+            return Err(DnsError { kind: DnsErrorKind::NameTooLong, offset });
+        }
+        self.labels.push(label);
+        Ok(())
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &[u8]> {
+        self.labels.iter().map(|l| l.as_slice())
+    }
+
+    // The root label is not counted
+    pub fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    // The name with its first label removed, the root has no parent
+    pub fn parent(&self) -> Option<Name> {
+        if self.is_root() {
+            return None;
+        }
+        Some(self.suffix(1))
+    }
+
+    // The name with its first `skip` labels removed
+    pub(crate) fn suffix(&self, skip: usize) -> Name {
+        Name { labels: self.labels[skip.min(self.labels.len())..].to_vec() }
+    }
+
+    // The canonical form DNSSEC signs and sorts by (RFC 4034 section 6.2)
+    pub fn to_lowercase(&self) -> Name {
+        Name { labels: self.labels.iter().map(|l| l.to_ascii_lowercase()).collect() }
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.labels.first().is_some_and(|l| l == b"*")
+    }
+
+    // `*.self`, the caller makes sure there is room for the extra label
+    pub(crate) fn wildcard(&self) -> Name {
+        let mut labels = vec![b"*".to_vec()];
+        labels.extend(self.labels.iter().cloned());
+        Name { labels }
+    }
+
+    // Canonical DNS name order, labels compared from the root down as lowercase bytes
+    // (RFC 4034 section 6.1)
+    pub fn canonical_cmp(&self, other: &Name) -> Ordering {
+        for (a, b) in self.labels.iter().rev().zip(other.labels.iter().rev()) {
+            let order = a.iter().map(u8::to_ascii_lowercase).cmp(b.iter().map(u8::to_ascii_lowercase));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        self.labels.len().cmp(&other.labels.len())
+    }
+
+    // The longest name both `self` and `other` are equal to or below
+    pub fn common_ancestor(&self, other: &Name) -> Name {
+        let common = self.labels.iter().rev().zip(other.labels.iter().rev())
+            .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+            .count();
+        self.suffix(self.labels.len() - common)
+    }
+
+    // True when `self` is `other` or sits anywhere below it
+    pub fn is_subdomain_of(&self, other: &Name) -> bool {
+        if other.labels.len() > self.labels.len() {
+            return false;
+        }
+        self.labels.iter().rev().zip(other.labels.iter().rev())
+            .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+
+    // The uncompressed wire form, ending with the root label
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.wire_len());
+        for label in self.labels.iter() {
+            data.push(label.len() as u8);
+            data.extend_from_slice(label);
+        }
+        data.push(0);
+        data
+    }
+
+    pub fn wire_len(&self) -> usize {
+        self.labels.iter().map(|l| l.len() + 1).sum::<usize>() + 1
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.labels.len() == other.labels.len()
+            && self.labels.iter().zip(other.labels.iter()).all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for label in self.labels.iter() {
+            state.write_usize(label.len());
+            for b in label {
+                state.write_u8(b.to_ascii_lowercase());
+            }
+        }
+        state.write_usize(self.labels.len());
+    }
+}
+
+// True when `s` ends with a dot that isn't escaped
+fn is_absolute(s: &str) -> bool {
+    if s == "." {
+        return true;
+    }
+    let Some(rest) = s.strip_suffix('.') else {
+        return false;
+    };
+    let backslashes = rest.bytes().rev().take_while(|&b| b == b'\\').count();
+    backslashes % 2 == 0
+}
+
+// Lets builders take either a parsed name or its presentation format, Unicode names are
+// converted to A-labels
+pub trait IntoName {
+    fn into_name(self) -> Result<Name, DnsError>;
+}
+
+impl IntoName for Name {
+    fn into_name(self) -> Result<Name, DnsError> {
+        Ok(self)
+    }
+}
+
+impl IntoName for &Name {
+    fn into_name(self) -> Result<Name, DnsError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoName for &str {
+    fn into_name(self) -> Result<Name, DnsError> {
+        Name::from_unicode(self)
+    }
+}
+
+impl IntoName for String {
+    fn into_name(self) -> Result<Name, DnsError> {
+        Name::from_unicode(&self)
+    }
+}
+
+impl FromStr for Name {
+    type Err = DnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Name::from_ascii(s)
+    }
+}
+
+// Names travel as their presentation format rather than as raw labels
+#[cfg(feature = "serde")]
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Name {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Name::from_ascii(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+// Writes one label in presentation format, escaping anything a zone file would misread
+pub(crate) fn fmt_label(f: &mut Formatter<'_>, label: &[u8]) -> fmt::Result {
+    for &b in label {
+        match b {
+            b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' => write!(f, "\\{}", b as char)?,
+            0x21..=0x7e => write!(f, "{}", b as char)?,
+            _ => write!(f, "\\{:03}", b)?,
+        }
+    }
+    Ok(())
+}
+
+// The Unicode form of an A-label, None for any other label or one that doesn't decode
+fn unicode_label(label: &[u8]) -> Option<String> {
+    if label.len() < 4 || !label[..4].eq_ignore_ascii_case(b"xn--") {
+        return None;
+    }
+    let label = std::str::from_utf8(label).ok()?;
+    match idna::domain_to_unicode(label) {
+        (unicode, Ok(())) => Some(unicode),
+        _ => None,
+    }
+}
+
+// `{:#}` shows A-labels as Unicode (RFC 5890), other labels are written as usual
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return write!(f, ".");
+        }
+        for label in self.labels.iter() {
+            match unicode_label(label) {
+                Some(unicode) if f.alternate() => write!(f, "{}", unicode)?,
+                _ => fmt_label(f, label)?,
+            }
+            write!(f, ".")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn name(s: &str) -> Name {
+        Name::from_ascii(s).unwrap()
+    }
+
+    fn kind(s: &str) -> DnsErrorKind {
+        Name::from_ascii(s).unwrap_err().kind
+    }
+
+    #[test]
+    fn escapes() {
+        let n = name("a\\.b.example.com.");
+        assert_eq!(n.label_count(), 3);
+        assert_eq!(n.labels().next(), Some(&b"a.b"[..]));
+        assert_eq!(n.to_string(), "a\\.b.example.com.");
+        assert_eq!(name("\\065\\000x."), Name::from_ascii("A\\000X").unwrap());
+        assert_eq!(name("\\000\\032\\\\.").to_string(), "\\000\\032\\\\.");
+        assert_eq!(kind("\\256."), DnsErrorKind::BadEscape);
+        assert_eq!(kind("\\12x."), DnsErrorKind::BadEscape);
+        assert_eq!(kind("a\\"), DnsErrorKind::BadEscape);
+    }
+
+    #[test]
+    fn empty_labels() {
+        assert!(name(".").is_root());
+        assert_eq!(kind(""), DnsErrorKind::EmptyLabel);
+        assert_eq!(kind("a..b."), DnsErrorKind::EmptyLabel);
+        assert_eq!(kind(".a."), DnsErrorKind::EmptyLabel);
+    }
+
+    #[test]
+    fn length_limits() {
+        let label = "a".repeat(MAX_LABEL_LEN);
+        assert_eq!(name(&label).wire_len(), 65);
+        assert_eq!(kind(&format!("{}a", label)), DnsErrorKind::LabelTooLong);
+        // Three 63 byte labels and one of 61 fill the 255 bytes exactly
+        let longest = format!("{0}.{0}.{0}.{1}.", label, "a".repeat(61));
+        assert_eq!(name(&longest).wire_len(), MAX_NAME_LEN);
+        assert_eq!(kind(&format!("a.{}", longest)), DnsErrorKind::NameTooLong);
+        // Escapes count as the single byte they stand for
+        assert_eq!(name(&"\\097".repeat(63)).wire_len(), 65);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        assert_eq!(name("WWW.Example.COM."), name("www.example.com"));
+        assert_ne!(name("www.example.com."), name("www.example.net."));
+        let set: HashSet<Name> = [name("Example.com."), name("EXAMPLE.COM.")].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        // The original case is kept
+        assert_eq!(name("Example.COM").to_string(), "Example.COM.");
+    }
+
+    #[test]
+    fn hierarchy() {
+        let n = name("www.example.com.");
+        assert_eq!(n.parent(), Some(name("example.com.")));
+        assert_eq!(Name::root().parent(), None);
+        assert!(n.is_subdomain_of(&name("EXAMPLE.com.")));
+        assert!(n.is_subdomain_of(&n));
+        assert!(n.is_subdomain_of(&Name::root()));
+        assert!(!n.is_subdomain_of(&name("ww.example.com.")));
+        assert!(!name("com.").is_subdomain_of(&n));
+        assert_eq!(n.label_count(), 3);
+    }
+
+    #[test]
+    fn idna() {
+        let n = Name::from_unicode("Bücher.de").unwrap();
+        assert_eq!(n.to_string(), "xn--bcher-kva.de.");
+        assert_eq!(format!("{:#}", n), "bücher.de.");
+        assert_eq!(n.to_unicode(), "bücher.de.");
+        assert_eq!("bücher.de".into_name().unwrap(), n);
+        // ASCII is read as it is, case and escapes included
+        assert_eq!(Name::from_unicode("WWW.a\\.b").unwrap().to_string(), "WWW.a\\.b.");
+        assert_eq!(Name::from_unicode("a\u{200d}.de").unwrap_err().kind, DnsErrorKind::BadIdn);
+    }
+}
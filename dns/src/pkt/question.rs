@@ -1,107 +1,312 @@
 use std::fmt;
 use std::fmt::Formatter;
-use bitvec::order::Msb0;
-use bitvec::prelude::BitVec;
-use bitvec::view::{BitView};
-use nom::IResult;
-use crate::pkt::{NBitSlice, parse_name, Serializable, take_u16};
-use strum_macros::{EnumString,Display};
-
+use crate::pkt::{parse_name, Serializable};
+use crate::pkt::codec::{Buffer, Reader};
+use crate::pkt::compress::Compressor;
+use crate::pkt::name::Name;
+use crate::pkt::error::{DnsError, EncodeError};
+use strum_macros::EnumString;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Question {
-    pub(crate) qname: String,
+    pub(crate) qname: Name,
     pub(crate) qtype: Qtype,
     pub(crate) qclass: Qclass,
 }
 
-#[derive(Debug, EnumString, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Qtype {
     #[strum(ascii_case_insensitive)]
     A,
     #[strum(ascii_case_insensitive)]
-    CNAME
+    NS,
+    #[strum(ascii_case_insensitive)]
+    CNAME,
+    #[strum(ascii_case_insensitive)]
+    SOA,
+    #[strum(ascii_case_insensitive)]
+    PTR,
+    #[strum(ascii_case_insensitive)]
+    MX,
+    #[strum(ascii_case_insensitive)]
+    TXT,
+    #[strum(ascii_case_insensitive)]
+    AAAA,
+    #[strum(ascii_case_insensitive)]
+    SRV,
+    #[strum(ascii_case_insensitive)]
+    OPT,
+    // DNSSEC (RFC 4034, RFC 5155, RFC 7344)
+    #[strum(ascii_case_insensitive)]
+    DS,
+    #[strum(ascii_case_insensitive)]
+    RRSIG,
+    #[strum(ascii_case_insensitive)]
+    NSEC,
+    #[strum(ascii_case_insensitive)]
+    DNSKEY,
+    #[strum(ascii_case_insensitive)]
+    NSEC3,
+    #[strum(ascii_case_insensitive)]
+    NSEC3PARAM,
+    #[strum(ascii_case_insensitive)]
+    CDS,
+    #[strum(ascii_case_insensitive)]
+    CDNSKEY,
+    // Transaction signatures, only ever the last record of a message (RFC 8945)
+    #[strum(ascii_case_insensitive)]
+    TSIG,
+    #[strum(ascii_case_insensitive)]
+    CAA,
+    // Every type, in questions and dynamic updates
+    #[strum(ascii_case_insensitive)]
+    ANY,
+    // Any type we don't model, its rdata is kept opaque (RFC 3597)
+    #[strum(disabled)]
+    Unknown(u16)
 }
 
 impl Qtype {
-    pub(crate) fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Self> {
-        let (data, qtype) = take_u16(data).unwrap();
-        match qtype {
-            0x0001 =>  Ok((data, Qtype::A)),
-            0x0005 => Ok((data, Qtype::CNAME)),
-            _ => {panic!("error parsing qtype: {}", qtype)}
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<Self, DnsError> {
+        Ok(Qtype::from(data.read_u16()?))
+    }
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u16(u16::from(*self))
+    }
+}
+
+impl From<u16> for Qtype {
+    fn from(value: u16) -> Self {
+        match value {
+            0x0001 => Qtype::A,
+            0x0002 => Qtype::NS,
+            0x0005 => Qtype::CNAME,
+            0x0006 => Qtype::SOA,
+            0x000c => Qtype::PTR,
+            0x000f => Qtype::MX,
+            0x0010 => Qtype::TXT,
+            0x001c => Qtype::AAAA,
+            0x0021 => Qtype::SRV,
+            0x0029 => Qtype::OPT,
+            0x002b => Qtype::DS,
+            0x002e => Qtype::RRSIG,
+            0x002f => Qtype::NSEC,
+            0x0030 => Qtype::DNSKEY,
+            0x0032 => Qtype::NSEC3,
+            0x0033 => Qtype::NSEC3PARAM,
+            0x003b => Qtype::CDS,
+            0x003c => Qtype::CDNSKEY,
+            0x00fa => Qtype::TSIG,
+            0x00ff => Qtype::ANY,
+            0x0101 => Qtype::CAA,
+            _ => Qtype::Unknown(value),
         }
     }
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
+}
+
+impl From<Qtype> for u16 {
+    fn from(value: Qtype) -> Self {
+        match value {
+            Qtype::A => 1,
+            Qtype::NS => 2,
+            Qtype::CNAME => 5,
+            Qtype::SOA => 6,
+            Qtype::PTR => 12,
+            Qtype::MX => 15,
+            Qtype::TXT => 16,
+            Qtype::AAAA => 28,
+            Qtype::SRV => 33,
+            Qtype::OPT => 41,
+            Qtype::DS => 43,
+            Qtype::RRSIG => 46,
+            Qtype::NSEC => 47,
+            Qtype::DNSKEY => 48,
+            Qtype::NSEC3 => 50,
+            Qtype::NSEC3PARAM => 51,
+            Qtype::CDS => 59,
+            Qtype::CDNSKEY => 60,
+            Qtype::TSIG => 250,
+            Qtype::ANY => 255,
+            Qtype::CAA => 257,
+            Qtype::Unknown(ty) => ty,
+        }
+    }
+}
+
+impl fmt::Display for Qtype {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Qtype::A => {data.extend_from_bitslice(1u16.view_bits::<Msb0>())}
-            Qtype::CNAME => {data.extend_from_bitslice(5u16.view_bits::<Msb0>())}
+            Qtype::Unknown(ty) => write!(f, "TYPE{}", ty),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
 
-#[derive(Debug, EnumString, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Qclass {
     #[strum(ascii_case_insensitive)]
-    IN
+    IN,
+    #[strum(serialize = "CH", serialize = "CHAOS", ascii_case_insensitive)]
+    CH,
+    #[strum(serialize = "HS", serialize = "HESIOD", ascii_case_insensitive)]
+    HS,
+    // Only used in dynamic update (RFC 2136)
+    #[strum(ascii_case_insensitive)]
+    NONE,
+    #[strum(serialize = "ANY", serialize = "*", ascii_case_insensitive)]
+    ANY,
+    #[strum(disabled)]
+    Unknown(u16)
 }
 
 impl Qclass {
-    pub(crate) fn deserialize(data: NBitSlice) -> IResult<NBitSlice, Self> {
-        let (data, qtype) = take_u16(data).unwrap();
-        match qtype {
-            0x0001 =>  Ok((data, Qclass::IN)),
-            _ => {panic!("error parsing qclass")}
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<Self, DnsError> {
+        Ok(Qclass::from(data.read_u16()?))
+    }
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        data.put_u16(u16::from(*self))
+    }
+}
+
+impl From<u16> for Qclass {
+    fn from(value: u16) -> Self {
+        match value {
+            0x0001 => Qclass::IN,
+            0x0003 => Qclass::CH,
+            0x0004 => Qclass::HS,
+            0x00fe => Qclass::NONE,
+            0x00ff => Qclass::ANY,
+            _ => Qclass::Unknown(value),
+        }
+    }
+}
+
+impl From<Qclass> for u16 {
+    fn from(value: Qclass) -> Self {
+        match value {
+            Qclass::IN => 1,
+            Qclass::CH => 3,
+            Qclass::HS => 4,
+            Qclass::NONE => 254,
+            Qclass::ANY => 255,
+            Qclass::Unknown(class) => class,
         }
     }
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
+}
+
+impl fmt::Display for Qclass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Qclass::IN => {data.extend_from_bitslice(1u16.view_bits::<Msb0>())}
+            Qclass::Unknown(class) => write!(f, "CLASS{}", class),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
 
 impl Serializable for Question {
-    fn serialize(&self, data: &mut BitVec<u8, Msb0>) {
-        data.extend(name_to_vec(&self.qname));
-        self.qtype.serialize(data);
-        self.qclass.serialize(data);
+    fn serialize(&self, data: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let start = data.len();
+        let result = self.serialize_with(data, &mut Compressor::disabled());
+        if result.is_err() {
+            data.truncate(start);
+        }
+        result
     }
 }
 
 impl Question {
-    pub fn deserialize<'a>(data: (&'a [u8], usize), raw_data: &[u8]) -> IResult<NBitSlice<'a>, Self> {
-        let (data, qname) = parse_name(data, raw_data).unwrap();
-        let (data, qtype) = Qtype::deserialize(data).unwrap();
-        let (data, qclass) = Qclass::deserialize(data).unwrap();
-        Ok((data, Question {
-            qname,
-            qtype,
-            qclass,
-        }))
+    pub(crate) fn deserialize(data: &mut Reader) -> Result<Self, DnsError> {
+        Ok(Question {
+            qname: parse_name(data)?,
+            qtype: Qtype::deserialize(data)?,
+            qclass: Qclass::deserialize(data)?,
+        })
     }
+    pub(crate) fn serialize_with(&self, data: &mut impl Buffer, names: &mut Compressor) -> Result<(), EncodeError> {
+        names.write_name(data, &self.qname)?;
+        self.qtype.serialize(data)?;
+        self.qclass.serialize(data)
+    }
+
     pub fn new() -> Question {
         Question {
-            qname: "".to_string(),
+            qname: Name::root(),
             qtype: Qtype::A,
             qclass: Qclass::IN
         }
     }
+
+    pub fn qname(&self) -> &Name {
+        &self.qname
+    }
+
+    pub fn qtype(&self) -> Qtype {
+        self.qtype
+    }
+
+    pub fn qclass(&self) -> Qclass {
+        self.qclass
+    }
 }
 
 
-fn name_to_vec(value: &str) -> Vec<u8> {
-    let split = value.split(".");
-    let mut data = vec![];
-    for s in split {
-        data.push(s.len().to_be_bytes()[7]);
-        data.extend_from_slice(s.as_bytes());
+impl Default for Question {
+    fn default() -> Self {
+        Question::new()
     }
-    data.push(0);
-    data
 }
 
 impl fmt::Display for Question {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return writeln!(f, "{:#}\t{}\t{}", self.qname, self.qtype, self.qclass);
+        }
         writeln!(f, "{}\t{}\t{}", self.qname, self.qtype, self.qclass)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_types_and_classes() {
+        for value in [0u16, 1, 99, 255, 0xff00, u16::MAX] {
+            let bytes = value.to_be_bytes();
+            let ty = Qtype::deserialize(&mut Reader::new(&bytes)).unwrap();
+            let class = Qclass::deserialize(&mut Reader::new(&bytes)).unwrap();
+            let mut data = vec![];
+            ty.serialize(&mut data).unwrap();
+            class.serialize(&mut data).unwrap();
+            assert_eq!(data, [bytes, bytes].concat());
+        }
+        assert_eq!(Qtype::deserialize(&mut Reader::new(&[0xff, 0])).unwrap(), Qtype::Unknown(65280));
+        assert_eq!(Qtype::Unknown(65280).to_string(), "TYPE65280");
+        assert_eq!(Qclass::deserialize(&mut Reader::new(&[0, 42])).unwrap(), Qclass::Unknown(42));
+        assert_eq!(Qclass::Unknown(42).to_string(), "CLASS42");
+    }
+
+    #[test]
+    fn classes() {
+        for (text, class, value) in [("IN", Qclass::IN, 1), ("CH", Qclass::CH, 3), ("chaos", Qclass::CH, 3),
+            ("HS", Qclass::HS, 4), ("Hesiod", Qclass::HS, 4), ("NONE", Qclass::NONE, 254),
+            ("ANY", Qclass::ANY, 255), ("*", Qclass::ANY, 255)] {
+            assert_eq!(text.parse::<Qclass>(), Ok(class));
+            assert_eq!(u16::from(class), value);
+            assert_eq!(Qclass::from(value), class);
+        }
+    }
+
+    #[test]
+    fn chaos_question() {
+        let question = Question { qname: Name::from_ascii("version.bind.").unwrap(), qtype: Qtype::TXT, qclass: Qclass::CH };
+        let mut data = vec![];
+        question.serialize(&mut data).unwrap();
+        assert_eq!(data[data.len() - 4..], [0, 16, 0, 3]);
+        let parsed = Question::deserialize(&mut Reader::new(&data)).unwrap();
+        assert_eq!((parsed.qname, parsed.qtype, parsed.qclass), (question.qname, Qtype::TXT, Qclass::CH));
+    }
+}
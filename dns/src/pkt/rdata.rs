@@ -0,0 +1,359 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use crate::pkt::question::Qtype;
+use crate::pkt::name::Name;
+use crate::pkt::edns::EdnsOption;
+use crate::pkt::dnssec::{Dnskey, Ds, Nsec, Nsec3, Nsec3Param, Rrsig};
+use crate::pkt::tsig::Tsig;
+use crate::pkt::compress::{write_uncompressed, Compressor};
+use crate::pkt::parse_name;
+use crate::pkt::codec::{put_sized, Buffer, Reader};
+use crate::pkt::error::{DnsError, DnsErrorKind, EncodeError};
+
+// The typed data carried by a resource record
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RData {
+    A(Ipv4Addr),
+    Ns(Name),
+    Cname(Name),
+    Soa {
+        mname: Name,
+        rname: Name,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    Ptr(Name),
+    Mx { preference: u16, exchange: Name },
+    // Each entry is one <character-string>
+    Txt(Vec<Vec<u8>>),
+    Aaaa(Ipv6Addr),
+    Srv { priority: u16, weight: u16, port: u16, target: Name },
+    Caa { flags: u8, tag: String, value: Vec<u8> },
+    // The options of an EDNS OPT pseudo-record, see `Edns`
+    Opt(Vec<EdnsOption>),
+    Ds(Ds),
+    Rrsig(Rrsig),
+    Nsec(Nsec),
+    Dnskey(Dnskey),
+    Nsec3(Nsec3),
+    Nsec3Param(Nsec3Param),
+    // The child's copies of its DS and DNSKEY for the parent to pick up (RFC 7344)
+    Cds(Ds),
+    Cdnskey(Dnskey),
+    // Only found at the end of a message, see `Message::tsig`
+    Tsig(Tsig),
+    // The rdata of a type we don't model, kept as received (RFC 3597). Also the missing rdata of
+    // the records dynamic updates use to stand for a whole RRset or name, see `Answer::deserialize`
+    Unknown { ty: u16, data: Vec<u8> },
+}
+
+impl RData {
+    // `rdata` must hold exactly the record's rdlength bytes
+    pub(crate) fn deserialize(ty: &Qtype, rdata: &mut Reader) -> Result<RData, DnsError> {
+        let start = *rdata;
+        let parsed = match ty {
+            Qtype::A => RData::A(Ipv4Addr::from(rdata.read_u32()?)),
+            Qtype::NS => RData::Ns(parse_name(rdata)?),
+            Qtype::CNAME => RData::Cname(parse_name(rdata)?),
+            Qtype::SOA => RData::Soa {
+                mname: parse_name(rdata)?,
+                rname: parse_name(rdata)?,
+                serial: rdata.read_u32()?,
+                refresh: rdata.read_u32()?,
+                retry: rdata.read_u32()?,
+                expire: rdata.read_u32()?,
+                minimum: rdata.read_u32()?,
+            },
+            Qtype::PTR => RData::Ptr(parse_name(rdata)?),
+            Qtype::MX => RData::Mx {
+                preference: rdata.read_u16()?,
+                exchange: parse_name(rdata)?,
+            },
+            Qtype::TXT => {
+                let mut strings = vec![];
+                while !rdata.is_empty() {
+                    strings.push(take_character_string(rdata)?.to_vec());
+                }
+                RData::Txt(strings)
+            }
+            Qtype::AAAA => RData::Aaaa(Ipv6Addr::from(rdata.read_array::<16>()?)),
+            Qtype::SRV => RData::Srv {
+                priority: rdata.read_u16()?,
+                weight: rdata.read_u16()?,
+                port: rdata.read_u16()?,
+                target: parse_name(rdata)?,
+            },
+            Qtype::CAA => {
+                let flags = rdata.read_u8()?;
+                let tag = match String::from_utf8(take_character_string(rdata)?.to_vec()) {
+                    Ok(tag) => tag,
+                    Err(_) => return Err(start.error(DnsErrorKind::BadRdata)),
+                };
+                RData::Caa { flags, tag, value: rdata.read_rest().to_vec() }
+            }
+            Qtype::OPT => {
+                let mut options = vec![];
+                while !rdata.is_empty() {
+                    options.push(EdnsOption::deserialize(rdata)?);
+                }
+                RData::Opt(options)
+            }
+            Qtype::DS => RData::Ds(Ds::deserialize(rdata)?),
+            Qtype::RRSIG => RData::Rrsig(Rrsig::deserialize(rdata)?),
+            Qtype::NSEC => RData::Nsec(Nsec::deserialize(rdata)?),
+            Qtype::DNSKEY => RData::Dnskey(Dnskey::deserialize(rdata)?),
+            Qtype::NSEC3 => RData::Nsec3(Nsec3::deserialize(rdata)?),
+            Qtype::NSEC3PARAM => RData::Nsec3Param(Nsec3Param::deserialize(rdata)?),
+            Qtype::CDS => RData::Cds(Ds::deserialize(rdata)?),
+            Qtype::CDNSKEY => RData::Cdnskey(Dnskey::deserialize(rdata)?),
+            Qtype::TSIG => RData::Tsig(Tsig::deserialize(rdata)?),
+            Qtype::ANY | Qtype::Unknown(_) => RData::Unknown { ty: u16::from(*ty), data: rdata.read_rest().to_vec() },
+        };
+        if !rdata.is_empty() {
+            return Err(rdata.error(DnsErrorKind::BadRdata));
+        }
+        Ok(parsed)
+    }
+
+    // Only the RFC 1035 types may have their names compressed (RFC 3597 section 4)
+    pub(crate) fn serialize(&self, data: &mut impl Buffer, names: &mut Compressor) -> Result<(), EncodeError> {
+        match self {
+            RData::A(addr) => data.put(&addr.octets()),
+            RData::Ns(name) | RData::Cname(name) | RData::Ptr(name) => names.write_name(data, name),
+            RData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => {
+                names.write_name(data, mname)?;
+                names.write_name(data, rname)?;
+                for n in [serial, refresh, retry, expire, minimum] {
+                    data.put_u32(*n)?;
+                }
+                Ok(())
+            }
+            RData::Mx { preference, exchange } => {
+                data.put_u16(*preference)?;
+                names.write_name(data, exchange)
+            }
+            RData::Txt(strings) => {
+                for s in strings {
+                    put_sized(data, s)?;
+                }
+                Ok(())
+            }
+            RData::Aaaa(addr) => data.put(&addr.octets()),
+            RData::Srv { priority, weight, port, target } => {
+                for n in [priority, weight, port] {
+                    data.put_u16(*n)?;
+                }
+                // SRV targets must not be compressed (RFC 2782)
+                write_uncompressed(data, target)
+            }
+            RData::Caa { flags, tag, value } => {
+                data.put_u8(*flags)?;
+                put_sized(data, tag.as_bytes())?;
+                data.put(value)
+            }
+            RData::Opt(options) => {
+                for o in options {
+                    o.serialize(data)?;
+                }
+                Ok(())
+            }
+            RData::Ds(ds) | RData::Cds(ds) => ds.serialize(data),
+            RData::Rrsig(rrsig) => rrsig.serialize(data),
+            RData::Nsec(nsec) => nsec.serialize(data),
+            RData::Dnskey(key) | RData::Cdnskey(key) => key.serialize(data),
+            RData::Nsec3(nsec3) => nsec3.serialize(data),
+            RData::Nsec3Param(param) => param.serialize(data),
+            RData::Tsig(tsig) => tsig.serialize(data),
+            RData::Unknown { data: rdata, .. } => data.put(rdata),
+        }
+    }
+
+    pub fn qtype(&self) -> Qtype {
+        match self {
+            RData::A(_) => Qtype::A,
+            RData::Ns(_) => Qtype::NS,
+            RData::Cname(_) => Qtype::CNAME,
+            RData::Soa { .. } => Qtype::SOA,
+            RData::Ptr(_) => Qtype::PTR,
+            RData::Mx { .. } => Qtype::MX,
+            RData::Txt(_) => Qtype::TXT,
+            RData::Aaaa(_) => Qtype::AAAA,
+            RData::Srv { .. } => Qtype::SRV,
+            RData::Caa { .. } => Qtype::CAA,
+            RData::Opt(_) => Qtype::OPT,
+            RData::Ds(_) => Qtype::DS,
+            RData::Rrsig(_) => Qtype::RRSIG,
+            RData::Nsec(_) => Qtype::NSEC,
+            RData::Dnskey(_) => Qtype::DNSKEY,
+            RData::Nsec3(_) => Qtype::NSEC3,
+            RData::Nsec3Param(_) => Qtype::NSEC3PARAM,
+            RData::Cds(_) => Qtype::CDS,
+            RData::Cdnskey(_) => Qtype::CDNSKEY,
+            RData::Tsig(_) => Qtype::TSIG,
+            RData::Unknown { ty, .. } => Qtype::from(*ty),
+        }
+    }
+}
+
+// A <character-string> is a length byte followed by up to 255 bytes
+pub(crate) fn take_character_string<'a>(data: &mut Reader<'a>) -> Result<&'a [u8], DnsError> {
+    let len = data.read_u8()?;
+    data.read_bytes(len as usize)
+}
+
+fn fmt_character_string(f: &mut Formatter<'_>, s: &[u8]) -> fmt::Result {
+    write!(f, "\"")?;
+    for &b in s {
+        match b {
+            b'"' | b'\\' => write!(f, "\\{}", b as char)?,
+            0x20..=0x7e => write!(f, "{}", b as char)?,
+            _ => write!(f, "\\{:03}", b)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{}", addr),
+            RData::Ns(name) | RData::Cname(name) | RData::Ptr(name) => write!(f, "{}", name),
+            RData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => {
+                write!(f, "{} {} {} {} {} {} {}", mname, rname, serial, refresh, retry, expire, minimum)
+            }
+            RData::Mx { preference, exchange } => write!(f, "{} {}", preference, exchange),
+            RData::Txt(strings) => {
+                for (i, s) in strings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    fmt_character_string(f, s)?;
+                }
+                Ok(())
+            }
+            RData::Aaaa(addr) => write!(f, "{}", addr),
+            RData::Srv { priority, weight, port, target } => write!(f, "{} {} {} {}", priority, weight, port, target),
+            RData::Caa { flags, tag, value } => {
+                write!(f, "{} {} ", flags, tag)?;
+                fmt_character_string(f, value)
+            }
+            RData::Opt(options) => {
+                for (i, o) in options.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", o)?;
+                }
+                Ok(())
+            }
+            RData::Ds(ds) | RData::Cds(ds) => write!(f, "{}", ds),
+            RData::Rrsig(rrsig) => write!(f, "{}", rrsig),
+            RData::Nsec(nsec) => write!(f, "{}", nsec),
+            RData::Dnskey(key) | RData::Cdnskey(key) => write!(f, "{}", key),
+            RData::Nsec3(nsec3) => write!(f, "{}", nsec3),
+            RData::Nsec3Param(param) => write!(f, "{}", param),
+            RData::Tsig(tsig) => write!(f, "{}", tsig),
+            // The generic \# <length> <hex> form from RFC 3597 section 5
+            RData::Unknown { data, .. } => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    write!(f, " ")?;
+                }
+                for b in data {
+                    write!(f, "{:02X}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::answer::Answer;
+    use crate::pkt::question::Qclass;
+    use crate::pkt::Serializable;
+
+    fn name(s: &str) -> Name {
+        Name::from_ascii(s).unwrap()
+    }
+
+    fn round_trip(rdata: RData) {
+        let record = Answer::new(name("example.com."), Qclass::IN, 300, rdata.clone());
+        let mut data = vec![];
+        record.serialize(&mut data).unwrap();
+        let parsed = Answer::deserialize(&mut Reader::new(&data)).unwrap();
+        assert_eq!(parsed.rdata(), &rdata);
+    }
+
+    #[test]
+    fn record_types() {
+        round_trip(RData::Aaaa("2001:db8::1".parse().unwrap()));
+        round_trip(RData::Mx { preference: 10, exchange: name("mail.example.com.") });
+        round_trip(RData::Ns(name("ns1.example.com.")));
+        round_trip(RData::Soa {
+            mname: name("ns1.example.com."),
+            rname: name("hostmaster.example.com."),
+            serial: 2024010101,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        });
+        round_trip(RData::Txt(vec![b"v=spf1 -all".to_vec(), vec![], vec![0, 255]]));
+        round_trip(RData::Ptr(name("host.example.com.")));
+        round_trip(RData::Srv { priority: 0, weight: 5, port: 5060, target: name("sip.example.com.") });
+        round_trip(RData::Caa { flags: 128, tag: "issue".into(), value: b"ca.example.net".to_vec() });
+    }
+
+    #[test]
+    fn unknown_rdata() {
+        let rdata = RData::Unknown { ty: 65280, data: vec![0xde, 0xad, 0xbe, 0xef] };
+        assert_eq!(rdata.qtype(), Qtype::Unknown(65280));
+        assert_eq!(rdata.to_string(), "\\# 4 DEADBEEF");
+        assert_eq!(RData::Unknown { ty: 65280, data: vec![] }.to_string(), "\\# 0");
+        let record = Answer::new(name("example.com."), Qclass::Unknown(42), 300, rdata.clone());
+        let mut data = vec![];
+        record.serialize(&mut data).unwrap();
+        let parsed = Answer::deserialize(&mut Reader::new(&data)).unwrap();
+        assert_eq!(parsed.rdata(), &rdata);
+        assert_eq!(parsed.to_string(), record.to_string());
+    }
+
+    #[test]
+    fn mx_wire_form() {
+        let mut data = vec![];
+        let rdata = RData::Mx { preference: 10, exchange: name("mx.") };
+        rdata.serialize(&mut data, &mut Compressor::disabled()).unwrap();
+        assert_eq!(data, [0, 10, 2, b'm', b'x', 0]);
+        assert_eq!(RData::deserialize(&Qtype::MX, &mut Reader::new(&data)).unwrap(), rdata);
+    }
+
+    #[test]
+    fn trailing_rdata() {
+        let data = [192, 0, 2, 1, 0];
+        let e = RData::deserialize(&Qtype::A, &mut Reader::new(&data)).unwrap_err();
+        assert_eq!(e, DnsError { kind: DnsErrorKind::BadRdata, offset: 4 });
+    }
+
+    #[test]
+    fn string_too_long() {
+        let mut data = vec![];
+        let rdata = RData::Txt(vec![vec![b'a'; 256]]);
+        assert_eq!(rdata.serialize(&mut data, &mut Compressor::disabled()), Err(EncodeError::StringTooLong(256)));
+    }
+
+    #[test]
+    fn rdata_too_long() {
+        let record = Answer::new(name("example.com."), Qclass::IN, 300, RData::Txt(vec![vec![b'a'; 255]; 257]));
+        let mut data = vec![];
+        assert_eq!(record.serialize(&mut data), Err(EncodeError::RdataTooLong(65792)));
+        assert!(data.is_empty());
+    }
+}
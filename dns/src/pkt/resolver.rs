@@ -0,0 +1,622 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::pkt::answer::Answer;
+use crate::pkt::denial::{self, Denial};
+use crate::pkt::dnssec::{DigestType, Ds};
+use crate::pkt::edns::Edns;
+use crate::pkt::error::{DnsError, EncodeError, ValidationError};
+use crate::pkt::header::Rcode;
+use crate::pkt::message::Message;
+use crate::pkt::name::{IntoName, Name};
+use crate::pkt::question::{Qclass, Qtype};
+use crate::pkt::rdata::RData;
+use crate::pkt::validate::{is_supported, is_supported_digest, verify_ds, verify_rrset};
+
+// The root zone's KSK-2017, as published by IANA
+pub const ROOT_ANCHOR: &str = ". 172800 IN DS 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D";
+
+const MAX_REFERRALS: usize = 32;
+const MAX_CNAMES: usize = 8;
+// How deep looking up the address of a name server without glue may nest
+const MAX_DEPTH: usize = 4;
+
+// The DNSSEC status of an answer (RFC 4033 section 5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Security {
+    Secure,
+    // An unsigned delegation was proven between the trust anchor and the answer
+    Insecure,
+    // The answer should have validated and didn't
+    Bogus(ValidationError),
+    // No trust anchor covers the name
+    Indeterminate,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub message: Message,
+    pub security: Security,
+}
+
+#[derive(Debug)]
+pub enum ResolveError {
+    Io(io::Error),
+    Malformed(DnsError),
+    // A response whose ID or question doesn't match the query
+    Mismatch,
+    // Every server for a zone answered with this rcode
+    ServerFailure(Rcode),
+    // A referral to servers whose addresses couldn't be found
+    NoServers,
+    TooManyReferrals,
+    // The query couldn't be put on the wire
+    Encode(EncodeError),
+    // The system's random number generator failed, so there is no ID to send the query with
+    NoRandomness,
+}
+
+// Where a zone stands on the way down from the trust anchor
+#[derive(Clone)]
+enum Trust {
+    // The zone's DNSKEY RRset, validated
+    Secure(Vec<Answer>),
+    // The DS records the zone's DNSKEY RRset has yet to be checked against
+    Pending(Vec<Ds>),
+    Insecure,
+    Bogus(ValidationError),
+    Indeterminate,
+}
+
+// An iterative resolver that follows referrals from the root and validates what it is given on
+// the way down, checking each zone's DNSKEY RRset against the DS records its parent signed
+pub struct Resolver {
+    roots: Vec<SocketAddr>,
+    redirects: Vec<(IpAddr, SocketAddr)>,
+    anchors: Vec<(Name, Ds)>,
+    timeout: Duration,
+    now: Option<u32>,
+}
+
+impl Resolver {
+    // `roots` are the root servers, or the stand-ins playing them
+    pub fn new(roots: Vec<SocketAddr>) -> Resolver {
+        Resolver {
+            roots,
+            redirects: vec![],
+            anchors: vec![],
+            timeout: Duration::from_secs(2),
+            now: None,
+        }
+    }
+
+    // Sends what would go to port 53 of the name server at `ip` to `to` instead, for stand-ins
+    // found through referrals
+    pub fn redirect(mut self, ip: IpAddr, to: SocketAddr) -> Self {
+        self.redirects.push((ip, to));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Checks signatures as of this time, in seconds since 1970, instead of the clock's
+    pub fn now(mut self, now: u32) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    // A DS or DNSKEY record to trust, records of other types are ignored
+    pub fn trust_anchor(mut self, record: &Answer) -> Self {
+        let ds = match record.rdata() {
+            RData::Ds(ds) => Some(ds.clone()),
+            RData::Dnskey(key) => key.to_ds(record.name(), DigestType::SHA256).ok(),
+            _ => None,
+        };
+        if let Some(ds) = ds {
+            self.anchors.push((record.name().clone(), ds));
+        }
+        self
+    }
+
+    pub fn resolve(&self, name: impl IntoName, qtype: Qtype) -> Result<Response, ResolveError> {
+        let qname = name.into_name().map_err(ResolveError::Malformed)?;
+        let now = self.now.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as u32)
+        });
+        let mut response = self.iterate(&qname, qtype, now, 0)?;
+        // A CNAME into another zone is answered by that zone's servers
+        for _ in 0..MAX_CNAMES {
+            let Some(target) = cname_target(&response.message, &qname, qtype) else {
+                break;
+            };
+            let next = self.iterate(&target, qtype, now, 0)?;
+            let message = &mut response.message;
+            for record in next.message.answers() {
+                message.add_answer(record.clone());
+            }
+            message.authority.clear();
+            message.header.nscount = 0;
+            for record in next.message.authority() {
+                message.add_authority(record.clone());
+            }
+            message.set_rcode(next.message.rcode());
+            response.security = worst(response.security, next.security);
+        }
+        Ok(response)
+    }
+
+    fn iterate(&self, qname: &Name, qtype: Qtype, now: u32, depth: usize) -> Result<Response, ResolveError> {
+        let mut zone = Name::root();
+        let mut servers = self.roots.clone();
+        let mut trust = self.anchor_trust(&zone).unwrap_or(Trust::Indeterminate);
+        for _ in 0..MAX_REFERRALS {
+            if let Trust::Pending(ds) = &trust {
+                trust = self.zone_keys(&servers, &zone, ds, now)?;
+            }
+            let mut message = self.query(&servers, qname, qtype)?;
+            if let Some(child) = referral(&message, &zone, qname) {
+                trust = match trust {
+                    Trust::Secure(keys) => ds_trust(message.authority(), &zone, &child, &keys, now),
+                    other => other,
+                };
+                trust = self.anchor_trust(&child).unwrap_or(trust);
+                servers = self.servers_for(&message, &child, now, depth)?;
+                zone = child;
+                continue;
+            }
+            // A server that also serves a zone below this one answers for it without a referral
+            if let Some((child, below)) = self.cut_below(&servers, &message, &zone, &trust, qname, now)? {
+                trust = self.anchor_trust(&child).unwrap_or(below);
+                zone = child;
+                if let Trust::Pending(ds) = &trust {
+                    trust = self.zone_keys(&servers, &zone, ds, now)?;
+                }
+            }
+            drop_out_of_zone(&mut message, &zone);
+            let security = match trust {
+                Trust::Secure(keys) => check_response(&message, &zone, &keys, qname, qtype, now),
+                Trust::Insecure => Security::Insecure,
+                Trust::Bogus(e) => Security::Bogus(e),
+                Trust::Indeterminate | Trust::Pending(_) => Security::Indeterminate,
+            };
+            return Ok(Response { message, security });
+        }
+        Err(ResolveError::TooManyReferrals)
+    }
+
+    // Configured anchors take over from whatever the parent said
+    fn anchor_trust(&self, zone: &Name) -> Option<Trust> {
+        let ds: Vec<Ds> = self.anchors.iter()
+            .filter(|(owner, _)| owner == zone)
+            .map(|(_, ds)| ds.clone())
+            .collect();
+        (!ds.is_empty()).then_some(Trust::Pending(ds))
+    }
+
+    // Finds the zone cut between `zone` and the answer when the servers didn't refer us across it.
+    // A signed child shows itself as the signer, an unsigned one is looked for by asking for the DS
+    // of each name down to `qname`
+    fn cut_below(&self, servers: &[SocketAddr], message: &Message, zone: &Name, trust: &Trust, qname: &Name, now: u32)
+                 -> Result<Option<(Name, Trust)>, ResolveError> {
+        let signer = signer_below(message, zone, qname);
+        let Trust::Secure(keys) = trust else {
+            return Ok(signer.map(|s| (s, trust.clone())));
+        };
+        let candidates: Vec<Name> = match signer {
+            Some(signer) => vec![signer],
+            None if !is_signed(message) => (zone.label_count() + 1..=qname.label_count())
+                .map(|n| qname.suffix(qname.label_count() - n))
+                .collect(),
+            None => vec![],
+        };
+        for child in candidates {
+            let section = ds_section(&self.query(servers, &child, Qtype::DS)?);
+            let is_cut = !rrset(&section, &child, Qtype::DS).is_empty() || verified_denial(&section, zone, keys, now)
+                .is_ok_and(|records| denial::unsigned_delegation(zone, &records, &child).is_some());
+            if is_cut {
+                let trust = ds_trust(&section, zone, &child, keys, now);
+                return Ok(Some((child, trust)));
+            }
+        }
+        Ok(None)
+    }
+
+    // Fetches the zone's DNSKEY RRset, which must be signed by a key one of `ds` names
+    fn zone_keys(&self, servers: &[SocketAddr], zone: &Name, ds: &[Ds], now: u32) -> Result<Trust, ResolveError> {
+        // A DS we can check must match, without one the zone is as good as unsigned
+        let ds = supported(ds);
+        if ds.is_empty() {
+            return Ok(Trust::Insecure);
+        }
+        let message = self.query(servers, zone, Qtype::DNSKEY)?;
+        let keys = rrset(message.answers(), zone, Qtype::DNSKEY);
+        let trusted: Vec<Answer> = keys.iter()
+            .filter(|r| match r.rdata() {
+                RData::Dnskey(key) => ds.iter().any(|d| verify_ds(d, zone, key).is_ok()),
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        if trusted.is_empty() {
+            return Ok(Trust::Bogus(ValidationError::DsMismatch));
+        }
+        Ok(match verify_signed(message.answers(), &keys, zone, &trusted, now) {
+            Ok(_) => Trust::Secure(keys),
+            Err(ValidationError::MissingSignature | ValidationError::NoMatchingKey) => {
+                Trust::Bogus(ValidationError::NoTrustedKey)
+            }
+            Err(e) => Trust::Bogus(e),
+        })
+    }
+
+    // The addresses of the child's name servers, from glue or else looked up
+    fn servers_for(&self, message: &Message, child: &Name, now: u32, depth: usize) -> Result<Vec<SocketAddr>, ResolveError> {
+        let names: Vec<&Name> = message.authority().iter()
+            .filter(|r| r.name() == child)
+            .filter_map(|r| match r.rdata() {
+                RData::Ns(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        let mut servers: Vec<SocketAddr> = message.additional().iter()
+            .filter(|r| names.contains(&r.name()))
+            .filter_map(|r| self.address(r))
+            .collect();
+        if servers.is_empty() && depth < MAX_DEPTH {
+            for name in names {
+                if let Ok(response) = self.iterate(name, Qtype::A, now, depth + 1) {
+                    servers.extend(response.message.answers().iter().filter_map(|r| self.address(r)));
+                }
+                if !servers.is_empty() {
+                    break;
+                }
+            }
+        }
+        if servers.is_empty() {
+            return Err(ResolveError::NoServers);
+        }
+        Ok(servers)
+    }
+
+    fn address(&self, record: &Answer) -> Option<SocketAddr> {
+        let ip = match record.rdata() {
+            RData::A(addr) => IpAddr::V4(*addr),
+            RData::Aaaa(addr) => IpAddr::V6(*addr),
+            _ => return None,
+        };
+        let redirect = self.redirects.iter().find(|(from, _)| *from == ip).map(|(_, to)| *to);
+        Some(redirect.unwrap_or(SocketAddr::new(ip, 53)))
+    }
+
+    // Asks each server in turn until one gives a usable answer
+    fn query(&self, servers: &[SocketAddr], qname: &Name, qtype: Qtype) -> Result<Message, ResolveError> {
+        let query = Message::builder()
+            .id(random_id()?)
+            .question(qname, qtype, Qclass::IN)
+            .edns(Edns { dnssec_ok: true, ..Edns::default() })
+            .build()
+            .map_err(ResolveError::Malformed)?;
+        let mut error = ResolveError::NoServers;
+        for server in servers {
+            match exchange(*server, &query, self.timeout) {
+                Ok(m) if matches!(m.rcode(), Rcode::NoError | Rcode::NameError) => return Ok(m),
+                Ok(m) => error = ResolveError::ServerFailure(m.rcode()),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
+
+// Sends `query` over UDP and returns the response, retrying over TCP when it comes back truncated
+pub fn exchange(server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, ResolveError> {
+    let data = query.to_vec().map_err(ResolveError::Encode)?;
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::from(([0; 4], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0; 16], 0)),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(server)?;
+    socket.send(&data)?;
+    let mut buf = [0; 65535];
+    // Anything that isn't the answer to this query is dropped, it may be a late or forged reply.
+    // Garbage is dropped too rather than ending the wait, the real answer may still come until the
+    // timeout runs out
+    let deadline = Instant::now() + timeout;
+    let response = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+        }
+        socket.set_read_timeout(Some(left))?;
+        let len = match socket.recv(&mut buf) {
+            // Unix reports a read timeout as WouldBlock
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Err(io::Error::from(io::ErrorKind::TimedOut).into()),
+            result => result?,
+        };
+        match Message::deserialize(&buf[..len]) {
+            Ok(response) if answers(&response, query) => break response,
+            _ => continue,
+        }
+    };
+    if !response.flags().tc {
+        return Ok(response);
+    }
+    let mut stream = TcpStream::connect_timeout(&server, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let mut framed = (data.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(&data);
+    stream.write_all(&framed)?;
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut buf)?;
+    let response = Message::deserialize(&buf).map_err(ResolveError::Malformed)?;
+    if !answers(&response, query) {
+        return Err(ResolveError::Mismatch);
+    }
+    Ok(response)
+}
+
+fn answers(response: &Message, query: &Message) -> bool {
+    response.flags().qr && response.id() == query.id() && response.questions() == query.questions()
+}
+
+fn random_id() -> Result<u16, ResolveError> {
+    let mut id = [0; 2];
+    SystemRandom::new().fill(&mut id).map_err(|_| ResolveError::NoRandomness)?;
+    Ok(u16::from_be_bytes(id))
+}
+
+// The child zone a response delegates to, None when it is an answer
+fn referral(message: &Message, zone: &Name, qname: &Name) -> Option<Name> {
+    if message.rcode() != Rcode::NoError || !message.answers().is_empty() {
+        return None;
+    }
+    message.authority().iter()
+        .find(|r| r.ty() == Qtype::NS && r.name() != zone && r.name().is_subdomain_of(zone) && qname.is_subdomain_of(r.name()))
+        .map(|r| r.name().clone())
+}
+
+// A zone between `zone` and `qname` that signed part of the response, signers off to the side
+// belong to the rest of a CNAME chain
+fn signer_below(message: &Message, zone: &Name, qname: &Name) -> Option<Name> {
+    message.answers().iter().chain(message.authority())
+        .filter_map(|r| match r.rdata() {
+            RData::Rrsig(sig) => Some(&sig.signer_name),
+            _ => None,
+        })
+        .find(|signer| *signer != zone && signer.is_subdomain_of(zone) && qname.is_subdomain_of(signer))
+        .cloned()
+}
+
+fn is_signed(message: &Message) -> bool {
+    message.answers().iter().chain(message.authority()).any(|r| r.ty() == Qtype::RRSIG)
+}
+
+// The answer to a DS query holds the DS RRset, a negative one has its proof in the authority section
+fn ds_section(message: &Message) -> Vec<Answer> {
+    message.answers().iter().chain(message.authority()).cloned().collect()
+}
+
+// What the DS records for `child` in `section`, or the proof there are none, say about it
+fn ds_trust(section: &[Answer], zone: &Name, child: &Name, keys: &[Answer], now: u32) -> Trust {
+    let ds_set = rrset(section, child, Qtype::DS);
+    if !ds_set.is_empty() {
+        if let Err(e) = verify_signed(section, &ds_set, zone, keys, now) {
+            return Trust::Bogus(e);
+        }
+        let ds: Vec<Ds> = ds_set.iter()
+            .filter_map(|r| match r.rdata() {
+                RData::Ds(ds) => Some(ds.clone()),
+                _ => None,
+            })
+            .collect();
+        let ds = supported(&ds);
+        return if ds.is_empty() { Trust::Insecure } else { Trust::Pending(ds) };
+    }
+    match verified_denial(section, zone, keys, now) {
+        Ok(records) => match denial::unsigned_delegation(zone, &records, child) {
+            Some(_) => Trust::Insecure,
+            None => Trust::Bogus(ValidationError::MissingDenial),
+        },
+        Err(e) => Trust::Bogus(e),
+    }
+}
+
+// The DS records whose digest type and algorithm we can check, a zone signed only with ones we
+// can't is treated as unsigned (RFC 4035 section 5.2)
+fn supported(ds: &[Ds]) -> Vec<Ds> {
+    ds.iter()
+        .filter(|ds| is_supported(ds.algorithm) && is_supported_digest(ds.digest_type))
+        .cloned()
+        .collect()
+}
+
+// Every RRset in the answer must be signed by the zone, and an answer without data proven.
+// Records from other zones were dropped by `drop_out_of_zone`, a chain into one is followed from there
+fn check_response(message: &Message, zone: &Name, keys: &[Answer], qname: &Name, qtype: Qtype, now: u32) -> Security {
+    let mut insecure = false;
+    for set in rrsets(message.answers()) {
+        let owner = set[0].name();
+        let sig = match verify_signed(message.answers(), &set, zone, keys, now) {
+            Ok(sig) => sig,
+            Err(e) => return Security::Bogus(e),
+        };
+        // Expanded from a wildcard, the name itself must be shown not to exist
+        if (sig.labels as usize) < owner.label_count() - owner.is_wildcard() as usize {
+            let proof = verified_denial(message.authority(), zone, keys, now)
+                .map(|records| denial::no_closer_match(zone, &records, owner, sig.labels));
+            match proof {
+                Ok(Some(Denial::Secure)) => {}
+                Ok(Some(Denial::Insecure)) => insecure = true,
+                Ok(None) => return Security::Bogus(ValidationError::MissingDenial),
+                Err(e) => return Security::Bogus(e),
+            }
+        }
+    }
+    let target = chain_end(message, qname);
+    let answered = message.answers().iter().any(|r| r.name() == &target && (r.ty() == qtype || qtype == Qtype::CNAME));
+    // The rest of a CNAME chain the server left out is followed separately
+    if answered || cname_target(message, qname, qtype).is_some() {
+        return if insecure { Security::Insecure } else { Security::Secure };
+    }
+    let records = match verified_denial(message.authority(), zone, keys, now) {
+        Ok(records) => records,
+        Err(e) => return Security::Bogus(e),
+    };
+    let proof = match message.rcode() {
+        Rcode::NameError => denial::nxdomain(zone, &records, &target),
+        _ => denial::nodata(zone, &records, &target, qtype),
+    };
+    match proof {
+        Some(Denial::Secure) if !insecure => Security::Secure,
+        Some(_) => Security::Insecure,
+        None => Security::Bogus(ValidationError::MissingDenial),
+    }
+}
+
+// The name a chain of CNAMEs from `qname` ends at
+fn chain_end(message: &Message, qname: &Name) -> Name {
+    let mut name = qname.clone();
+    for _ in 0..MAX_CNAMES {
+        let next = message.answers().iter().find_map(|r| match r.rdata() {
+            RData::Cname(target) if r.name() == &name => Some(target.clone()),
+            _ => None,
+        });
+        match next {
+            Some(target) => name = target,
+            None => break,
+        }
+    }
+    name
+}
+
+// Where a CNAME chain stops short of the data without the server saying there is none, which
+// happens when the target is in another zone. A negative answer carries the SOA of a zone holding
+// the target, the rcode alone may have come from a zone whose records were dropped
+fn cname_target(message: &Message, qname: &Name, qtype: Qtype) -> Option<Name> {
+    if qtype == Qtype::CNAME {
+        return None;
+    }
+    let target = chain_end(message, qname);
+    let answered = message.answers().iter().any(|r| r.name() == &target && r.ty() == qtype);
+    let negative = message.authority().iter().any(|r| r.ty() == Qtype::SOA && target.is_subdomain_of(r.name()));
+    (target != *qname && !answered && !negative).then_some(target)
+}
+
+// Answer records owned outside `zone` can't be checked against its keys and may be forged, a
+// server answering for several zones puts them there when a CNAME chain crosses between them
+fn drop_out_of_zone(message: &mut Message, zone: &Name) {
+    message.answers.retain(|r| r.name().is_subdomain_of(zone));
+    message.header.ancount = message.answers.len() as u16;
+}
+
+// The NSEC and NSEC3 records in `section`, each RRset checked against the zone's keys
+fn verified_denial(section: &[Answer], zone: &Name, keys: &[Answer], now: u32) -> Result<Vec<Answer>, ValidationError> {
+    let mut records = vec![];
+    for set in rrsets(section) {
+        if matches!(set[0].ty(), Qtype::NSEC | Qtype::NSEC3) {
+            verify_signed(section, &set, zone, keys, now)?;
+            records.extend(set);
+        }
+    }
+    Ok(records)
+}
+
+// Tries each RRSIG in `section` the zone made over `rrset`, returning the one that verifies
+fn verify_signed(section: &[Answer], rrset: &[Answer], zone: &Name, keys: &[Answer], now: u32) -> Result<crate::pkt::dnssec::Rrsig, ValidationError> {
+    let mut result = Err(ValidationError::MissingSignature);
+    for r in section.iter().filter(|r| r.name() == rrset[0].name()) {
+        let RData::Rrsig(sig) = r.rdata() else {
+            continue;
+        };
+        if sig.type_covered != rrset[0].ty() || &sig.signer_name != zone {
+            continue;
+        }
+        match verify_rrset(rrset, sig, keys, now) {
+            Ok(()) => return Ok(sig.clone()),
+            Err(e) => result = Err(e),
+        }
+    }
+    result
+}
+
+fn rrset(section: &[Answer], name: &Name, ty: Qtype) -> Vec<Answer> {
+    section.iter().filter(|r| r.name() == name && r.ty() == ty).cloned().collect()
+}
+
+// Groups a section into RRsets, leaving out the signatures
+fn rrsets(section: &[Answer]) -> Vec<Vec<Answer>> {
+    let mut sets: Vec<Vec<Answer>> = vec![];
+    for r in section.iter().filter(|r| r.ty() != Qtype::RRSIG) {
+        match sets.iter_mut().find(|s| s[0].name() == r.name() && s[0].ty() == r.ty() && s[0].class() == r.class()) {
+            Some(set) => set.push(r.clone()),
+            None => sets.push(vec![r.clone()]),
+        }
+    }
+    sets
+}
+
+// Bogus outranks indeterminate, which outranks insecure, which outranks secure
+fn worst(a: Security, b: Security) -> Security {
+    let rank = |s: &Security| match s {
+        Security::Secure => 0,
+        Security::Insecure => 1,
+        Security::Indeterminate => 2,
+        Security::Bogus(_) => 3,
+    };
+    if rank(&b) > rank(&a) { b } else { a }
+}
+
+impl fmt::Display for Security {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Security::Secure => write!(f, "secure"),
+            Security::Insecure => write!(f, "insecure"),
+            Security::Bogus(e) => write!(f, "bogus: {}", e),
+            Security::Indeterminate => write!(f, "indeterminate"),
+        }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Io(e) => write!(f, "{}", e),
+            ResolveError::Malformed(e) => write!(f, "malformed response: {}", e),
+            ResolveError::Mismatch => write!(f, "response does not match the query"),
+            ResolveError::ServerFailure(rcode) => write!(f, "servers answered {}", rcode),
+            ResolveError::NoServers => write!(f, "no reachable name servers"),
+            ResolveError::TooManyReferrals => write!(f, "too many referrals"),
+            ResolveError::Encode(e) => write!(f, "unable to encode query: {}", e),
+            ResolveError::NoRandomness => write!(f, "unable to generate a query ID"),
+        }
+    }
+}
+
+impl Error for ResolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolveError::Io(e) => Some(e),
+            ResolveError::Malformed(e) => Some(e),
+            ResolveError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ResolveError {
+    fn from(e: io::Error) -> Self {
+        ResolveError::Io(e)
+    }
+}
@@ -0,0 +1,564 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use data_encoding::{Encoding, BASE32HEX_NOPAD, BASE64, HEXUPPER_PERMISSIVE};
+use crate::pkt::answer::Answer;
+use crate::pkt::codec::Reader;
+use crate::pkt::dnssec::{parse_time, Algorithm, DigestType, Dnskey, Ds, Nsec, Nsec3, Nsec3Param, Rrsig, TypeBitmap};
+use crate::pkt::error::{DnsError, DnsErrorKind};
+use crate::pkt::name::Name;
+use crate::pkt::question::{Qclass, Qtype};
+use crate::pkt::rdata::RData;
+
+// One field of a record in presentation format (RFC 1035 section 5.1). Quotes are removed
+// but escapes are left in, names and strings decode them differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub quoted: bool,
+    // Byte offset of the text in the input, reported on errors
+    pub offset: usize,
+}
+
+// What a record falls back on for the fields it leaves out
+#[derive(Debug, Clone)]
+pub struct Defaults {
+    // Names without a trailing dot are relative to this
+    pub origin: Name,
+    // The owner of a record whose line starts with whitespace
+    pub owner: Option<Name>,
+    pub ttl: Option<u32>,
+    pub class: Qclass,
+}
+
+impl Defaults {
+    pub fn new() -> Defaults {
+        Defaults {
+            origin: Name::root(),
+            owner: None,
+            ttl: None,
+            class: Qclass::IN,
+        }
+    }
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults::new()
+    }
+}
+
+fn err<T>(kind: DnsErrorKind, offset: usize) -> Result<T, DnsError> {
+    Err(error(kind, offset))
+}
+
+fn error(kind: DnsErrorKind, offset: usize) -> DnsError {
+    DnsError { kind, offset }
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'(' | b')' | b'"' | b';')
+}
+
+// Splits `s` into fields. Parentheses only group lines so they are dropped, comments run
+// from `;` to the end of the line
+pub fn tokenize(s: &str) -> Result<Vec<Token<'_>>, DnsError> {
+    let bytes = s.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    // Steps over a backslash and the character it escapes, which may be more than a byte
+    let skip_escape = |i: usize| i + 1 + s[i + 1..].chars().next().map_or(0, char::len_utf8);
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' | b'(' | b')' => i += 1,
+            b';' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                loop {
+                    match bytes.get(i) {
+                        None => return err(DnsErrorKind::UnterminatedQuote, start - 1),
+                        Some(b'"') => break,
+                        Some(b'\\') => i = skip_escape(i),
+                        Some(_) => i += 1,
+                    }
+                }
+                tokens.push(Token { text: &s[start..i], quoted: true, offset: start });
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len() && !is_delimiter(bytes[i]) {
+                    i = if bytes[i] == b'\\' { skip_escape(i) } else { i + 1 };
+                }
+                tokens.push(Token { text: &s[start..i], quoted: false, offset: start });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Decodes the `\X` and `\DDD` escapes of a <character-string>
+pub fn unescape(token: &Token) -> Result<Vec<u8>, DnsError> {
+    let bytes = token.text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let digits = &bytes[i + 1..bytes.len().min(i + 4)];
+        if digits.len() == 3 && digits.iter().all(u8::is_ascii_digit) {
+            let value = digits.iter().fold(0u32, |acc, d| acc * 10 + (d - b'0') as u32);
+            if value > 255 {
+                return err(DnsErrorKind::BadEscape, token.offset + i);
+            }
+            out.push(value as u8);
+            i += 4;
+        } else if digits.first().is_some_and(|c| !c.is_ascii_digit()) {
+            out.push(digits[0]);
+            i += 2;
+        } else {
+            return err(DnsErrorKind::BadEscape, token.offset + i);
+        }
+    }
+    Ok(out)
+}
+
+// Accepts a mnemonic or the generic TYPEnnn form (RFC 3597 section 5)
+pub fn parse_type(s: &str) -> Option<Qtype> {
+    match s.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("TYPE") => s[4..].parse::<u16>().ok().map(Qtype::from),
+        _ => s.parse().ok(),
+    }
+}
+
+// Accepts a mnemonic or the generic CLASSnnn form
+pub fn parse_class(s: &str) -> Option<Qclass> {
+    match s.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("CLASS") => s[5..].parse::<u16>().ok().map(Qclass::from),
+        _ => s.parse().ok(),
+    }
+}
+
+// Seconds, optionally written with BIND's w/d/h/m/s units such as `1h30m`
+pub fn parse_ttl(s: &str) -> Option<u32> {
+    if !s.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if let Ok(ttl) = s.parse() {
+        return Some(ttl);
+    }
+    let mut total: u32 = 0;
+    let mut n: u32 = 0;
+    let mut digits = false;
+    for c in s.chars() {
+        if let Some(d) = c.to_digit(10) {
+            n = n.checked_mul(10)?.checked_add(d)?;
+            digits = true;
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 604800,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        if !digits {
+            return None;
+        }
+        total = total.checked_add(n.checked_mul(unit)?)?;
+        n = 0;
+        digits = false;
+    }
+    if digits {
+        return None;
+    }
+    Some(total)
+}
+
+// Hands out the fields of a record one at a time
+struct Fields<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    pos: usize,
+    // Where a missing field is reported
+    end: usize,
+}
+
+impl<'t, 'a> Fields<'t, 'a> {
+    fn next(&mut self, what: &'static str) -> Result<Token<'a>, DnsError> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(*t)
+            }
+            None => err(DnsErrorKind::MissingField(what), self.end),
+        }
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn number<T: FromStr>(&mut self, what: &'static str) -> Result<T, DnsError> {
+        let t = self.next(what)?;
+        t.text.parse().map_err(|_| error(DnsErrorKind::BadField(what), t.offset))
+    }
+
+    fn ttl(&mut self, what: &'static str) -> Result<u32, DnsError> {
+        let t = self.next(what)?;
+        parse_ttl(t.text).ok_or_else(|| error(DnsErrorKind::BadField(what), t.offset))
+    }
+
+    fn name(&mut self, what: &'static str, origin: &Name) -> Result<Name, DnsError> {
+        let t = self.next(what)?;
+        Name::from_ascii_in(t.text, origin).map_err(|e| DnsError { offset: t.offset + e.offset, ..e })
+    }
+
+    fn string(&mut self, what: &'static str) -> Result<Vec<u8>, DnsError> {
+        let t = self.next(what)?;
+        let s = unescape(&t)?;
+        if s.len() > 255 {
+            return err(DnsErrorKind::BadField(what), t.offset);
+        }
+        Ok(s)
+    }
+
+    // Either the number or its mnemonic, such as 8 or RSASHA256
+    fn algorithm(&mut self) -> Result<Algorithm, DnsError> {
+        let t = self.next("algorithm")?;
+        match t.text.parse::<u8>() {
+            Ok(alg) => Ok(Algorithm::from(alg)),
+            Err(_) => t.text.parse().map_err(|_| error(DnsErrorKind::BadField("algorithm"), t.offset)),
+        }
+    }
+
+    fn digest_type(&mut self) -> Result<DigestType, DnsError> {
+        let t = self.next("digest type")?;
+        match t.text.parse::<u8>() {
+            Ok(ty) => Ok(DigestType::from(ty)),
+            Err(_) => t.text.parse().map_err(|_| error(DnsErrorKind::BadField("digest type"), t.offset)),
+        }
+    }
+
+    fn time(&mut self, what: &'static str) -> Result<u32, DnsError> {
+        let t = self.next(what)?;
+        parse_time(t.text).ok_or_else(|| error(DnsErrorKind::BadField(what), t.offset))
+    }
+
+    // Keys, signatures and digests may be split over any number of fields, all the rest are taken
+    fn encoded_rest(&mut self, what: &'static str, encoding: &Encoding) -> Result<Vec<u8>, DnsError> {
+        let first = self.next(what)?;
+        let mut text = first.text.to_string();
+        while let Some(t) = self.peek() {
+            text.push_str(t.text);
+            self.pos += 1;
+        }
+        encoding.decode(text.as_bytes()).map_err(|_| error(DnsErrorKind::BadField(what), first.offset))
+    }
+
+    // Hex, or `-` for no salt (RFC 5155 section 3.3)
+    fn salt(&mut self) -> Result<Vec<u8>, DnsError> {
+        let t = self.next("salt")?;
+        if t.text == "-" {
+            return Ok(vec![]);
+        }
+        match HEXUPPER_PERMISSIVE.decode(t.text.as_bytes()) {
+            Ok(salt) if !salt.is_empty() && salt.len() <= 255 => Ok(salt),
+            _ => err(DnsErrorKind::BadField("salt"), t.offset),
+        }
+    }
+
+    fn types(&mut self) -> Result<TypeBitmap, DnsError> {
+        let mut types = vec![];
+        while let Some(t) = self.peek() {
+            types.push(parse_type(t.text).ok_or_else(|| error(DnsErrorKind::UnknownType, t.offset))?);
+            self.pos += 1;
+        }
+        Ok(TypeBitmap::new(types))
+    }
+
+    fn finish(&self) -> Result<(), DnsError> {
+        match self.peek() {
+            Some(t) => err(DnsErrorKind::ExtraField, t.offset),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RData {
+    // Parses the rdata fields of a record of type `ty`, relative names are completed with `origin`
+    pub fn from_text(ty: Qtype, s: &str, origin: &Name) -> Result<RData, DnsError> {
+        let tokens = tokenize(s)?;
+        RData::from_tokens(ty, &tokens, s.len(), origin)
+    }
+
+    pub(crate) fn from_tokens(ty: Qtype, tokens: &[Token], end: usize, origin: &Name) -> Result<RData, DnsError> {
+        let mut f = Fields { tokens, pos: 0, end };
+        if f.peek().is_some_and(|t| t.text == "\\#" && !t.quoted) {
+            return generic(ty, f);
+        }
+        let rdata = match ty {
+            Qtype::A => {
+                let t = f.next("address")?;
+                RData::A(t.text.parse::<Ipv4Addr>().map_err(|_| error(DnsErrorKind::BadField("address"), t.offset))?)
+            }
+            Qtype::NS => RData::Ns(f.name("name server", origin)?),
+            Qtype::CNAME => RData::Cname(f.name("canonical name", origin)?),
+            Qtype::SOA => RData::Soa {
+                mname: f.name("primary name server", origin)?,
+                rname: f.name("mailbox", origin)?,
+                serial: f.number("serial")?,
+                refresh: f.ttl("refresh")?,
+                retry: f.ttl("retry")?,
+                expire: f.ttl("expire")?,
+                minimum: f.ttl("minimum")?,
+            },
+            Qtype::PTR => RData::Ptr(f.name("pointer", origin)?),
+            Qtype::MX => RData::Mx {
+                preference: f.number("preference")?,
+                exchange: f.name("exchange", origin)?,
+            },
+            Qtype::TXT => {
+                let mut strings = vec![f.string("text")?];
+                while !f.is_empty() {
+                    strings.push(f.string("text")?);
+                }
+                RData::Txt(strings)
+            }
+            Qtype::AAAA => {
+                let t = f.next("address")?;
+                RData::Aaaa(t.text.parse::<Ipv6Addr>().map_err(|_| error(DnsErrorKind::BadField("address"), t.offset))?)
+            }
+            Qtype::SRV => RData::Srv {
+                priority: f.number("priority")?,
+                weight: f.number("weight")?,
+                port: f.number("port")?,
+                target: f.name("target", origin)?,
+            },
+            Qtype::CAA => {
+                let flags = f.number("flags")?;
+                let t = f.next("tag")?;
+                if t.text.is_empty() || !t.text.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return err(DnsErrorKind::BadField("tag"), t.offset);
+                }
+                let value = unescape(&f.next("value")?)?;
+                RData::Caa { flags, tag: t.text.to_string(), value }
+            }
+            Qtype::DS | Qtype::CDS => {
+                let ds = Ds {
+                    key_tag: f.number("key tag")?,
+                    algorithm: f.algorithm()?,
+                    digest_type: f.digest_type()?,
+                    digest: f.encoded_rest("digest", &HEXUPPER_PERMISSIVE)?,
+                };
+                if ty == Qtype::DS { RData::Ds(ds) } else { RData::Cds(ds) }
+            }
+            Qtype::RRSIG => {
+                let t = f.next("type covered")?;
+                RData::Rrsig(Rrsig {
+                    type_covered: parse_type(t.text).ok_or_else(|| error(DnsErrorKind::UnknownType, t.offset))?,
+                    algorithm: f.algorithm()?,
+                    labels: f.number("labels")?,
+                    original_ttl: f.ttl("original TTL")?,
+                    expiration: f.time("expiration")?,
+                    inception: f.time("inception")?,
+                    key_tag: f.number("key tag")?,
+                    signer_name: f.name("signer", origin)?,
+                    signature: f.encoded_rest("signature", &BASE64)?,
+                })
+            }
+            Qtype::NSEC => RData::Nsec(Nsec {
+                next_name: f.name("next name", origin)?,
+                types: f.types()?,
+            }),
+            Qtype::DNSKEY | Qtype::CDNSKEY => {
+                let key = Dnskey {
+                    flags: f.number("flags")?,
+                    protocol: f.number("protocol")?,
+                    algorithm: f.algorithm()?,
+                    public_key: f.encoded_rest("public key", &BASE64)?,
+                };
+                if ty == Qtype::DNSKEY { RData::Dnskey(key) } else { RData::Cdnskey(key) }
+            }
+            Qtype::NSEC3 => {
+                let hash_algorithm = f.number("hash algorithm")?;
+                let flags = f.number("flags")?;
+                let iterations = f.number("iterations")?;
+                let salt = f.salt()?;
+                // Base32hex without padding, in either case
+                let t = f.next("next hashed owner")?;
+                let next_hashed = match BASE32HEX_NOPAD.decode(t.text.to_ascii_uppercase().as_bytes()) {
+                    Ok(hash) if !hash.is_empty() && hash.len() <= 255 => hash,
+                    _ => return err(DnsErrorKind::BadField("next hashed owner"), t.offset),
+                };
+                RData::Nsec3(Nsec3 { hash_algorithm, flags, iterations, salt, next_hashed, types: f.types()? })
+            }
+            Qtype::NSEC3PARAM => RData::Nsec3Param(Nsec3Param {
+                hash_algorithm: f.number("hash algorithm")?,
+                flags: f.number("flags")?,
+                iterations: f.number("iterations")?,
+                salt: f.salt()?,
+            }),
+            // OPT, TSIG and ANY never appear in zone files and other types have no text form, only \# will do
+            Qtype::OPT | Qtype::TSIG | Qtype::ANY | Qtype::Unknown(_) => {
+                return err(DnsErrorKind::MissingField("\\#"), f.peek().map_or(end, |t| t.offset));
+            }
+        };
+        f.finish()?;
+        Ok(rdata)
+    }
+}
+
+// The `\# <length> <hex>` form any type may use (RFC 3597 section 5)
+fn generic(ty: Qtype, mut f: Fields) -> Result<RData, DnsError> {
+    let start = f.next("\\#")?.offset;
+    let len: usize = f.number("rdata length")?;
+    let mut data = Vec::with_capacity(len);
+    while let Some(t) = f.peek() {
+        f.pos += 1;
+        let hex = t.text.as_bytes();
+        if hex.len() % 2 != 0 {
+            return err(DnsErrorKind::BadField("hex"), t.offset);
+        }
+        for (i, pair) in hex.chunks(2).enumerate() {
+            let byte = std::str::from_utf8(pair).ok().and_then(|p| u8::from_str_radix(p, 16).ok());
+            match byte {
+                Some(b) => data.push(b),
+                None => return err(DnsErrorKind::BadField("hex"), t.offset + i * 2),
+            }
+        }
+    }
+    if data.len() != len {
+        return err(DnsErrorKind::BadField("rdata length"), start);
+    }
+    // Known types are decoded so they come out the same as if they'd been written normally
+    let mut r = Reader::new(&data);
+    RData::deserialize(&ty, &mut r).map_err(|_| error(DnsErrorKind::BadRdata, start))
+}
+
+// Parses one record, `<owner> [<ttl>] [<class>] <type> <rdata>` with the TTL and class in
+// either order. A line starting with whitespace has no owner and reuses `defaults.owner`
+pub fn parse_record(s: &str, defaults: &Defaults) -> Result<Answer, DnsError> {
+    let tokens = tokenize(s)?;
+    let mut f = Fields { tokens: &tokens, pos: 0, end: s.len() };
+    let owner = if s.starts_with(|c: char| c.is_ascii_whitespace()) {
+        match &defaults.owner {
+            Some(owner) => owner.clone(),
+            None => return err(DnsErrorKind::MissingField("owner"), 0),
+        }
+    } else {
+        f.name("owner", &defaults.origin)?
+    };
+    let mut ttl = None;
+    let mut class = None;
+    for _ in 0..2 {
+        let t = match f.peek() {
+            Some(t) if !t.quoted => t,
+            _ => break,
+        };
+        if ttl.is_none() && t.text.starts_with(|c: char| c.is_ascii_digit()) {
+            ttl = Some(parse_ttl(t.text).ok_or_else(|| error(DnsErrorKind::BadField("TTL"), t.offset))?);
+        } else if class.is_none() && parse_class(t.text).is_some() {
+            class = parse_class(t.text);
+        } else {
+            break;
+        }
+        f.pos += 1;
+    }
+    let ttl = match ttl.or(defaults.ttl) {
+        Some(ttl) => ttl,
+        None => return err(DnsErrorKind::MissingField("TTL"), f.peek().map_or(s.len(), |t| t.offset)),
+    };
+    let t = f.next("type")?;
+    let ty = parse_type(t.text).ok_or_else(|| error(DnsErrorKind::UnknownType, t.offset))?;
+    let rdata = RData::from_tokens(ty, &tokens[f.pos..], s.len(), &defaults.origin)?;
+    Ok(Answer::new(owner, class.unwrap_or(defaults.class), ttl, rdata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(s: &str) -> Answer {
+        s.parse().unwrap()
+    }
+
+    fn kind(s: &str) -> DnsErrorKind {
+        s.parse::<Answer>().unwrap_err().kind
+    }
+
+    #[test]
+    fn round_trips() {
+        for s in [
+            "example.com.\t3600\tIN\tA\t192.0.2.1",
+            "example.com.\t3600\tIN\tAAAA\t2001:db8::1",
+            "example.com.\t3600\tIN\tMX\t10 mail.example.com.",
+            "example.com.\t3600\tIN\tNS\tns1.example.com.",
+            "example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. 1 7200 3600 1209600 300",
+            "example.com.\t3600\tIN\tTXT\t\"v=spf1 -all\" \"a \\\"quoted\\\" \\\\ string\" \"\\000\\255\"",
+            "1.2.0.192.in-addr.arpa.\t3600\tIN\tPTR\thost.example.com.",
+            "_sip._tcp.example.com.\t3600\tIN\tSRV\t0 5 5060 sip.example.com.",
+            "example.com.\t3600\tIN\tCAA\t0 issue \"ca.example.net\"",
+            "a\\.b.example.com.\t3600\tCH\tCNAME\t\\000.example.com.",
+            "example.com.\t3600\tIN\tTYPE65280\t\\# 3 ABCDEF",
+        ] {
+            assert_eq!(record(s).to_string(), format!("{}\n", s));
+        }
+    }
+
+    #[test]
+    fn field_order_and_defaults() {
+        let a = record("example.com. IN 1h30m A 192.0.2.1");
+        assert_eq!(a.ttl(), 5400);
+        assert_eq!(a.class(), Qclass::IN);
+        let defaults = Defaults {
+            origin: Name::from_ascii("example.com.").unwrap(),
+            owner: Some(Name::from_ascii("prev.example.com.").unwrap()),
+            ttl: Some(60),
+            class: Qclass::CH,
+        };
+        let b = parse_record("  MX ( 10 ; preference\n mail )", &defaults).unwrap();
+        assert_eq!(b.to_string(), "prev.example.com.\t60\tCH\tMX\t10 mail.example.com.\n");
+        let c = parse_record("@ TXT unquoted", &defaults).unwrap();
+        assert_eq!(c.name(), &defaults.origin);
+    }
+
+    #[test]
+    fn generic_rdata_for_known_type() {
+        assert_eq!(record("example.com. 60 IN A \\# 4 C0000201"), record("example.com. 60 IN A 192.0.2.1"));
+        assert_eq!(kind("example.com. 60 IN A \\# 5 C0000201"), DnsErrorKind::BadField("rdata length"));
+        assert_eq!(kind("example.com. 60 IN A \\# 3 C00002"), DnsErrorKind::BadRdata);
+    }
+
+    #[test]
+    fn errors() {
+        let e = "example.com. 60 IN A 192.0.2".parse::<Answer>().unwrap_err();
+        assert_eq!(e.offset, 21);
+        assert_eq!(kind("example.com. IN A 192.0.2.1"), DnsErrorKind::MissingField("TTL"));
+        assert_eq!(kind("example.com. 60 IN BOGUS x"), DnsErrorKind::UnknownType);
+        assert_eq!(kind("example.com. 60 IN A 192.0.2.1 extra"), DnsErrorKind::ExtraField);
+        assert_eq!(kind("example.com. 60 IN MX 10"), DnsErrorKind::MissingField("exchange"));
+        assert_eq!(kind("example.com. 60 IN TXT \"open"), DnsErrorKind::UnterminatedQuote);
+        assert_eq!(kind("example.com. 60 IN TXT \"\\256\""), DnsErrorKind::BadEscape);
+    }
+
+    #[test]
+    fn ttl_units() {
+        assert_eq!(parse_ttl("1w2d3h4m5s"), Some(788645));
+        assert_eq!(parse_ttl("90"), Some(90));
+        assert_eq!(parse_ttl("1x"), None);
+        assert_eq!(parse_ttl("h"), None);
+    }
+}
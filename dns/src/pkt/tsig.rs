@@ -0,0 +1,616 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::{SystemTime, UNIX_EPOCH};
+use data_encoding::BASE64;
+use ring::hmac;
+use crate::pkt::answer::Answer;
+use crate::pkt::codec::{rdata_len, Buffer, Reader};
+use crate::pkt::compress::write_uncompressed;
+use crate::pkt::error::{DnsError, EncodeError, TsigError};
+use crate::pkt::header::Header;
+use crate::pkt::message::Message;
+use crate::pkt::name::{IntoName, Name};
+use crate::pkt::question::Qclass;
+use crate::pkt::rdata::RData;
+use crate::pkt::{parse_name, skip_name};
+
+// How far apart the two clocks may be, the value RFC 8945 section 10 recommends
+pub const DEFAULT_FUDGE: u16 = 300;
+// A stream may leave this many messages in a row unsigned (RFC 8945 section 5.3.1)
+const MAX_UNSIGNED: usize = 99;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+// The TSIG record's error field (RFC 8945 section 4.2). Its values overlap the extended rcodes,
+// 16 is BADVERS there, so they get their own type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TsigRcode {
+    NoError,
+    BadSig,
+    BadKey,
+    BadTime,
+    // The MAC was truncated more than local policy allows
+    BadTrunc,
+    Other(u16),
+}
+
+// A secret shared with a server, known to both sides by its name
+#[derive(Clone, PartialEq, Eq)]
+pub struct TsigKey {
+    pub name: Name,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+// The rdata of a TSIG record (RFC 8945 section 4.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tsig {
+    pub algorithm: Name,
+    // Seconds since 1970, 48 bits on the wire
+    pub time_signed: u64,
+    pub fudge: u16,
+    pub mac: Vec<u8>,
+    // The ID the message had when it was signed, forwarders may change the header's
+    pub original_id: u16,
+    pub error: TsigRcode,
+    // The server's time when the error is BADTIME
+    pub other: Vec<u8>,
+}
+
+// Signs a message when it is serialized, see `Message::set_tsig`. The MAC is made over the
+// message exactly as it is written out, so anything may be changed up to that point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsigSigner {
+    key: TsigKey,
+    fudge: u16,
+    time: Option<u64>,
+    request_mac: Option<Vec<u8>>,
+    timers_only: bool,
+    error: TsigRcode,
+    other: Option<Vec<u8>>,
+}
+
+// Checks the signed messages that come back for a request, one at a time
+pub struct TsigVerifier {
+    key: TsigKey,
+    request_mac: Option<Vec<u8>>,
+    // The MAC of the last signed message of a stream, the next one follows on from it
+    prior_mac: Option<Vec<u8>>,
+    // Messages received since then without a TSIG record, they are covered by the next MAC
+    unsigned: Vec<Vec<u8>>,
+    now: Option<u64>,
+}
+
+impl TsigAlgorithm {
+    // The algorithm's name as it goes in the TSIG record
+    pub fn name(&self) -> Name {
+        let name = match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256.",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512.",
+        };
+        Name::from_ascii(name).unwrap_or_default()
+    }
+
+    pub fn from_name(name: &Name) -> Option<TsigAlgorithm> {
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512].into_iter().find(|a| a.name() == *name)
+    }
+
+    fn hmac(&self) -> hmac::Algorithm {
+        match self {
+            TsigAlgorithm::HmacSha256 => hmac::HMAC_SHA256,
+            TsigAlgorithm::HmacSha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+impl TsigKey {
+    pub fn new(name: impl IntoName, algorithm: TsigAlgorithm, secret: &[u8]) -> Result<TsigKey, DnsError> {
+        Ok(TsigKey { name: name.into_name()?, algorithm, secret: secret.to_vec() })
+    }
+}
+
+impl Tsig {
+    pub(crate) fn deserialize(rdata: &mut Reader) -> Result<Tsig, DnsError> {
+        let algorithm = parse_name(rdata)?;
+        let high = rdata.read_u16()? as u64;
+        let time_signed = high << 32 | rdata.read_u32()? as u64;
+        let fudge = rdata.read_u16()?;
+        let len = rdata.read_u16()?;
+        let mac = rdata.read_bytes(len as usize)?.to_vec();
+        let original_id = rdata.read_u16()?;
+        let error = TsigRcode::from(rdata.read_u16()?);
+        let len = rdata.read_u16()?;
+        let other = rdata.read_bytes(len as usize)?.to_vec();
+        Ok(Tsig { algorithm, time_signed, fudge, mac, original_id, error, other })
+    }
+
+    // The algorithm name is never compressed (RFC 8945 section 4.2)
+    pub(crate) fn serialize(&self, data: &mut impl Buffer) -> Result<(), EncodeError> {
+        write_uncompressed(data, &self.algorithm)?;
+        data.put(&time48(self.time_signed))?;
+        data.put_u16(self.fudge)?;
+        data.put_u16(rdata_len(self.mac.len())?)?;
+        data.put(&self.mac)?;
+        data.put_u16(self.original_id)?;
+        data.put_u16(u16::from(self.error))?;
+        data.put_u16(rdata_len(self.other.len())?)?;
+        data.put(&self.other)
+    }
+}
+
+impl TsigSigner {
+    pub fn new(key: TsigKey) -> TsigSigner {
+        TsigSigner {
+            key,
+            fudge: DEFAULT_FUDGE,
+            time: None,
+            request_mac: None,
+            timers_only: false,
+            error: TsigRcode::NoError,
+            other: None,
+        }
+    }
+
+    pub fn fudge(mut self, fudge: u16) -> Self {
+        self.fudge = fudge;
+        self
+    }
+
+    // Signs as of this time, in seconds since 1970, instead of the clock's
+    pub fn time(mut self, time: u64) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    // Signs a response, which covers the MAC of the request it answers
+    pub fn request_mac(mut self, mac: &[u8]) -> Self {
+        self.request_mac = Some(mac.to_vec());
+        self
+    }
+
+    // Signs a later message of a TCP stream, which covers the MAC of the one before it and only
+    // the timers of its own TSIG record
+    pub fn subsequent(mut self, prior_mac: &[u8]) -> Self {
+        self.request_mac = Some(prior_mac.to_vec());
+        self.timers_only = true;
+        self
+    }
+
+    // Answers a request that failed its TSIG check (RFC 8945 section 5.2), the message's rcode
+    // should be NOTAUTH. BADKEY and BADSIG go out without a MAC. A BADTIME response is signed
+    // with the request's time, set with `time`, and carries the server's in the other data
+    pub fn error(mut self, error: TsigRcode) -> Self {
+        self.error = error;
+        self
+    }
+
+    // The other data, in place of the server's time that BADTIME puts there
+    pub fn other(mut self, other: &[u8]) -> Self {
+        self.other = Some(other.to_vec());
+        self
+    }
+
+    // The TSIG record for `message`, already in wire form without its TSIG record
+    pub(crate) fn sign(&self, message: &[u8], id: u16) -> Answer {
+        let other = match &self.other {
+            Some(other) => other.clone(),
+            None if self.error == TsigRcode::BadTime => time48(now()).to_vec(),
+            None => vec![],
+        };
+        let mut tsig = Tsig {
+            algorithm: self.key.algorithm.name(),
+            time_signed: self.time.unwrap_or_else(now),
+            fudge: self.fudge,
+            mac: vec![],
+            original_id: id,
+            error: self.error,
+            other,
+        };
+        if !matches!(self.error, TsigRcode::BadKey | TsigRcode::BadSig) {
+            tsig.mac = mac(&self.key, self.request_mac.as_deref(), &[message], &tsig, self.timers_only);
+        }
+        Answer::new(self.key.name.clone(), Qclass::ANY, 0, RData::Tsig(tsig))
+    }
+}
+
+impl TsigVerifier {
+    // Verifies requests, which don't cover any earlier MAC
+    pub fn new(key: TsigKey) -> TsigVerifier {
+        TsigVerifier {
+            key,
+            request_mac: None,
+            prior_mac: None,
+            unsigned: vec![],
+            now: None,
+        }
+    }
+
+    // Verifies the responses to `request`, the signed request as it was sent
+    pub fn response_to(key: TsigKey, request: &[u8]) -> Result<TsigVerifier, TsigError> {
+        let request = Message::deserialize(request).map_err(TsigError::Malformed)?;
+        let Some(RData::Tsig(tsig)) = request.tsig().map(Answer::rdata) else {
+            return Err(TsigError::Unsigned);
+        };
+        Ok(TsigVerifier { request_mac: Some(tsig.mac.clone()), ..TsigVerifier::new(key) })
+    }
+
+    // Checks times against this one, in seconds since 1970, instead of the clock
+    pub fn now(mut self, now: u64) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    // Checks the next message received and returns it parsed. After the first, messages may
+    // come unsigned, they are checked along with the next one that is signed (RFC 8945 section 5.3.1)
+    pub fn verify(&mut self, data: &[u8]) -> Result<Message, TsigError> {
+        let message = Message::deserialize(data).map_err(TsigError::Malformed)?;
+        let Some(record) = message.tsig() else {
+            if self.prior_mac.is_some() && self.unsigned.len() < MAX_UNSIGNED {
+                self.unsigned.push(data.to_vec());
+                return Ok(message);
+            }
+            return Err(TsigError::Unsigned);
+        };
+        let RData::Tsig(tsig) = record.rdata() else {
+            return Err(TsigError::Unsigned);
+        };
+        // BADKEY and BADSIG come back without a MAC, there's nothing to check
+        if tsig.error != TsigRcode::NoError && tsig.mac.is_empty() {
+            return Err(rejected(tsig.error));
+        }
+        if record.name() != &self.key.name || TsigAlgorithm::from_name(&tsig.algorithm) != Some(self.key.algorithm) {
+            return Err(TsigError::BadKey);
+        }
+        // The MAC was made over the message as it was before the TSIG record went on
+        let mut signed = data[..tsig_offset(data).map_err(TsigError::Malformed)?].to_vec();
+        signed[..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+        signed[10..12].copy_from_slice(&(message.header().arcount() - 1).to_be_bytes());
+        let mut messages: Vec<&[u8]> = self.unsigned.iter().map(Vec::as_slice).collect();
+        messages.push(&signed);
+        let expected = match &self.prior_mac {
+            Some(prior) => mac(&self.key, Some(prior), &messages, tsig, true),
+            None => mac(&self.key, self.request_mac.as_deref(), &messages, tsig, false),
+        };
+        // A MAC may be truncated, but not below half its length or 10 bytes (RFC 8945 section 5.2.2.1)
+        let len = tsig.mac.len();
+        if len < (expected.len() / 2).max(10) || len > expected.len() || !constant_eq(&expected[..len], &tsig.mac) {
+            return Err(TsigError::BadSig);
+        }
+        if tsig.error != TsigRcode::NoError {
+            return Err(rejected(tsig.error));
+        }
+        if self.now.unwrap_or_else(now).abs_diff(tsig.time_signed) > tsig.fudge as u64 {
+            return Err(TsigError::BadTime);
+        }
+        self.prior_mac = Some(tsig.mac.clone());
+        self.unsigned.clear();
+        Ok(message)
+    }
+
+    // A stream has to end with a signed message
+    pub fn finish(&self) -> Result<(), TsigError> {
+        if self.prior_mac.is_none() || !self.unsigned.is_empty() {
+            return Err(TsigError::Unsigned);
+        }
+        Ok(())
+    }
+}
+
+// What the MAC covers (RFC 8945 section 4.3): the MAC this message follows on from, the
+// messages themselves without their TSIG record, then the TSIG variables or, in the later
+// messages of a stream, only its timers
+fn mac(key: &TsigKey, prior: Option<&[u8]>, messages: &[&[u8]], tsig: &Tsig, timers_only: bool) -> Vec<u8> {
+    let mut ctx = hmac::Context::with_key(&hmac::Key::new(key.algorithm.hmac(), &key.secret));
+    if let Some(prior) = prior {
+        ctx.update(&(prior.len() as u16).to_be_bytes());
+        ctx.update(prior);
+    }
+    for message in messages {
+        ctx.update(message);
+    }
+    if !timers_only {
+        ctx.update(&key.name.to_lowercase().to_wire());
+        ctx.update(&u16::from(Qclass::ANY).to_be_bytes());
+        ctx.update(&0u32.to_be_bytes());
+        ctx.update(&tsig.algorithm.to_lowercase().to_wire());
+    }
+    ctx.update(&time48(tsig.time_signed));
+    ctx.update(&tsig.fudge.to_be_bytes());
+    if !timers_only {
+        ctx.update(&u16::from(tsig.error).to_be_bytes());
+        ctx.update(&(tsig.other.len() as u16).to_be_bytes());
+        ctx.update(&tsig.other);
+    }
+    ctx.sign().as_ref().to_vec()
+}
+
+// Where the last record, the TSIG record, starts
+fn tsig_offset(data: &[u8]) -> Result<usize, DnsError> {
+    let mut r = Reader::new(data);
+    let header = Header::deserialize(&mut r)?;
+    for _ in 0..header.qdcount() {
+        skip_name(&mut r)?;
+        r.read_u32()?;
+    }
+    let records = header.ancount() as usize + header.nscount() as usize + header.arcount() as usize;
+    for _ in 1..records {
+        skip_name(&mut r)?;
+        r.read_bytes(8)?;
+        let len = r.read_u16()?;
+        r.read_bytes(len as usize)?;
+    }
+    Ok(r.position())
+}
+
+fn rejected(error: TsigRcode) -> TsigError {
+    match error {
+        TsigRcode::BadSig => TsigError::BadSig,
+        TsigRcode::BadKey => TsigError::BadKey,
+        TsigRcode::BadTime => TsigError::BadTime,
+        rcode => TsigError::Rejected(rcode),
+    }
+}
+
+fn constant_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn time48(time: u64) -> [u8; 6] {
+    let bytes = time.to_be_bytes();
+    [bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+impl From<u16> for TsigRcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => TsigRcode::NoError,
+            16 => TsigRcode::BadSig,
+            17 => TsigRcode::BadKey,
+            18 => TsigRcode::BadTime,
+            22 => TsigRcode::BadTrunc,
+            _ => TsigRcode::Other(value),
+        }
+    }
+}
+
+impl From<TsigRcode> for u16 {
+    fn from(value: TsigRcode) -> Self {
+        match value {
+            TsigRcode::NoError => 0,
+            TsigRcode::BadSig => 16,
+            TsigRcode::BadKey => 17,
+            TsigRcode::BadTime => 18,
+            TsigRcode::BadTrunc => 22,
+            TsigRcode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for TsigRcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TsigRcode::Other(code) => write!(f, "RCODE{}", code),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl fmt::Display for TsigAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// The secret is left out so keys can be logged
+impl fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TsigKey")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Tsig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {} {} {} {} {}", self.algorithm, self.time_signed, self.fudge, self.mac.len(),
+               BASE64.encode(&self.mac), self.original_id, self.error, self.other.len())?;
+        if !self.other.is_empty() {
+            write!(f, " {}", BASE64.encode(&self.other))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::header::Rcode;
+    use crate::pkt::question::Qtype;
+
+    const T: u64 = 1_700_000_000;
+
+    fn key() -> TsigKey {
+        TsigKey::new("key.example.", TsigAlgorithm::HmacSha256, b"0123456789abcdef").unwrap()
+    }
+
+    fn query() -> Message {
+        Message::builder().id(42).question("example.", Qtype::SOA, Qclass::IN).build().unwrap()
+    }
+
+    fn signed(mut message: Message, signer: TsigSigner) -> Vec<u8> {
+        message.set_tsig(Some(signer));
+        message.to_vec().unwrap()
+    }
+
+    fn tsig_of(data: &[u8]) -> Tsig {
+        match Message::deserialize(data).unwrap().tsig().map(Answer::rdata) {
+            Some(RData::Tsig(tsig)) => tsig.clone(),
+            _ => panic!("no TSIG record"),
+        }
+    }
+
+    // The message with `tsig` put on as its TSIG record, for MACs the signer doesn't make
+    fn with_tsig(data: &[u8], tsig: Tsig) -> Vec<u8> {
+        let mut message = Message::deserialize(data).unwrap();
+        message.set_tsig(None);
+        message.add_additional(Answer::new(key().name, Qclass::ANY, 0, RData::Tsig(tsig)));
+        message.to_vec().unwrap()
+    }
+
+    #[test]
+    fn request_and_response() {
+        let request = signed(query(), TsigSigner::new(key()).time(T));
+        let parsed = TsigVerifier::new(key()).now(T + 10).verify(&request).unwrap();
+        assert_eq!(parsed.id(), 42);
+        let request_mac = tsig_of(&request).mac;
+        assert_eq!(request_mac.len(), 32);
+
+        let mut response = signed(Message::response_to(&parsed), TsigSigner::new(key()).time(T).request_mac(&request_mac));
+        // A forwarder changes the ID after signing, the MAC covers the original one in the TSIG record
+        response[0..2].copy_from_slice(&7u16.to_be_bytes());
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        assert_eq!(verifier.verify(&response).unwrap().id(), 7);
+        assert_eq!(tsig_of(&response).original_id, 42);
+
+        // The second message of a stream covers the first one's MAC
+        let next = signed(Message::response_to(&parsed), TsigSigner::new(key()).time(T + 1).subsequent(&tsig_of(&response).mac));
+        assert!(verifier.verify(&next).is_ok());
+        assert_eq!(verifier.finish(), Ok(()));
+        // Signed as a later message of a stream it doesn't verify as the first
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        assert_eq!(verifier.verify(&next).err(), Some(TsigError::BadSig));
+    }
+
+    #[test]
+    fn tampered() {
+        let mut request = signed(query(), TsigSigner::new(key()).time(T));
+        request[2] ^= 1;
+        assert_eq!(TsigVerifier::new(key()).now(T).verify(&request).err(), Some(TsigError::BadSig));
+        let unsigned = query().to_vec().unwrap();
+        assert_eq!(TsigVerifier::new(key()).now(T).verify(&unsigned).err(), Some(TsigError::Unsigned));
+    }
+
+    #[test]
+    fn badtime() {
+        let request = signed(query(), TsigSigner::new(key()).time(T));
+        assert_eq!(TsigVerifier::new(key()).now(T + 301).verify(&request).err(), Some(TsigError::BadTime));
+        assert!(TsigVerifier::new(key()).now(T - 300).verify(&request).is_ok());
+
+        // The server answers with NOTAUTH, signing with the request's time and giving its own
+        let mut response = Message::response_to(&Message::deserialize(&request).unwrap());
+        response.set_rcode(Rcode::NotAuth);
+        let signer = TsigSigner::new(key()).time(T).request_mac(&tsig_of(&request).mac).error(TsigRcode::BadTime);
+        let response = signed(response, signer);
+        let tsig = tsig_of(&response);
+        assert_eq!((tsig.error, tsig.time_signed, tsig.other.len()), (TsigRcode::BadTime, T, 6));
+        let server_time = u64::from_be_bytes([0, 0, tsig.other[0], tsig.other[1], tsig.other[2], tsig.other[3],
+                                              tsig.other[4], tsig.other[5]]);
+        assert!(server_time.abs_diff(now()) < 60);
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        assert_eq!(verifier.verify(&response).err(), Some(TsigError::BadTime));
+
+        // One that doesn't cover the request's MAC is refused like any other
+        let signer = TsigSigner::new(key()).time(T).request_mac(&[0; 32]).error(TsigRcode::BadTime).other(&time48(T + 400));
+        let forged = signed(Message::response_to(&query()), signer);
+        assert_eq!(tsig_of(&forged).other, time48(T + 400));
+        assert_eq!(verifier.verify(&forged).err(), Some(TsigError::BadSig));
+    }
+
+    #[test]
+    fn unsigned_errors() {
+        let request = signed(query(), TsigSigner::new(key()).time(T));
+        for (error, expected) in [(TsigRcode::BadKey, TsigError::BadKey), (TsigRcode::BadSig, TsigError::BadSig)] {
+            let mut response = Message::response_to(&query());
+            response.set_rcode(Rcode::NotAuth);
+            let response = signed(response, TsigSigner::new(key()).time(T).error(error));
+            let tsig = tsig_of(&response);
+            assert!(tsig.mac.is_empty() && tsig.other.is_empty());
+            let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+            assert_eq!(verifier.verify(&response).err(), Some(expected));
+        }
+        // Other errors are signed and handed back as they are
+        let signer = TsigSigner::new(key()).time(T).request_mac(&tsig_of(&request).mac).error(TsigRcode::BadTrunc);
+        let response = signed(Message::response_to(&query()), signer);
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        assert_eq!(verifier.verify(&response).err(), Some(TsigError::Rejected(TsigRcode::BadTrunc)));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let other_name = TsigKey::new("other.example.", TsigAlgorithm::HmacSha256, b"0123456789abcdef").unwrap();
+        let other_algorithm = TsigKey { algorithm: TsigAlgorithm::HmacSha512, ..key() };
+        for wrong in [other_name, other_algorithm] {
+            let request = signed(query(), TsigSigner::new(wrong).time(T));
+            assert_eq!(TsigVerifier::new(key()).now(T).verify(&request).err(), Some(TsigError::BadKey));
+        }
+        let other_secret = TsigKey { secret: b"fedcba9876543210".to_vec(), ..key() };
+        let request = signed(query(), TsigSigner::new(other_secret).time(T));
+        assert_eq!(TsigVerifier::new(key()).now(T).verify(&request).err(), Some(TsigError::BadSig));
+    }
+
+    #[test]
+    fn truncated_mac() {
+        let request = signed(query(), TsigSigner::new(key()).time(T));
+        let full = tsig_of(&request);
+        // Half of SHA-256's 32 bytes is the shortest allowed
+        for (len, ok) in [(16, true), (20, true), (15, false), (10, false), (0, false)] {
+            let tsig = Tsig { mac: full.mac[..len].to_vec(), ..full.clone() };
+            let result = TsigVerifier::new(key()).now(T).verify(&with_tsig(&request, tsig));
+            assert_eq!(result.is_ok(), ok, "{} bytes", len);
+            if !ok {
+                assert_eq!(result.err(), Some(TsigError::BadSig));
+            }
+        }
+        let long = Tsig { mac: [&full.mac[..], &[0]].concat(), ..full.clone() };
+        assert_eq!(TsigVerifier::new(key()).now(T).verify(&with_tsig(&request, long)).err(), Some(TsigError::BadSig));
+        // Truncated to the wrong bytes
+        let mut wrong = full.mac[..16].to_vec();
+        wrong[15] ^= 1;
+        let tsig = Tsig { mac: wrong, ..full };
+        assert_eq!(TsigVerifier::new(key()).now(T).verify(&with_tsig(&request, tsig)).err(), Some(TsigError::BadSig));
+    }
+
+    #[test]
+    fn unsigned_stream_messages() {
+        let request = signed(query(), TsigSigner::new(key()).time(T));
+        let first = signed(Message::response_to(&query()), TsigSigner::new(key()).time(T).request_mac(&tsig_of(&request).mac));
+        let plain = Message::response_to(&query()).to_vec().unwrap();
+
+        // The first message must be signed
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        assert_eq!(verifier.verify(&plain).err(), Some(TsigError::Unsigned));
+
+        // Up to 99 may follow unsigned, the next signed one covers them all
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        verifier.verify(&first).unwrap();
+        for _ in 0..MAX_UNSIGNED {
+            verifier.verify(&plain).unwrap();
+        }
+        assert_eq!(verifier.finish(), Err(TsigError::Unsigned));
+        let mut tsig = Tsig { time_signed: T + 1, mac: vec![], ..tsig_of(&first) };
+        let messages: Vec<&[u8]> = vec![&plain; MAX_UNSIGNED + 1];
+        tsig.mac = mac(&key(), Some(&tsig_of(&first).mac), &messages, &tsig, true);
+        verifier.verify(&with_tsig(&plain, tsig)).unwrap();
+        assert_eq!(verifier.finish(), Ok(()));
+
+        // A hundredth in a row is refused
+        let mut verifier = TsigVerifier::response_to(key(), &request).unwrap().now(T);
+        verifier.verify(&first).unwrap();
+        for _ in 0..MAX_UNSIGNED {
+            verifier.verify(&plain).unwrap();
+        }
+        assert_eq!(verifier.verify(&plain).err(), Some(TsigError::Unsigned));
+    }
+}
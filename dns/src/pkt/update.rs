@@ -0,0 +1,314 @@
+use crate::pkt::answer::Answer;
+use crate::pkt::builder::MessageBuilder;
+use crate::pkt::error::{DnsError, DnsErrorKind};
+use crate::pkt::header::Opcode;
+use crate::pkt::message::Message;
+use crate::pkt::name::{IntoName, Name};
+use crate::pkt::question::{Qclass, Qtype, Question};
+use crate::pkt::rdata::RData;
+use crate::pkt::tsig::TsigSigner;
+
+// Puts a dynamic update together (RFC 2136). The message's sections are read differently: the
+// question names the zone, the answer section holds the prerequisites and the authority section
+// the updates. Like `MessageBuilder`, nothing is checked until `build`
+pub struct UpdateBuilder {
+    id: u16,
+    zone: Name,
+    class: Qclass,
+    prerequisites: Vec<Entry>,
+    updates: Vec<Entry>,
+    signer: Option<TsigSigner>,
+    error: Option<DnsError>,
+}
+
+// The class a record is sent with, the zone's is only known once the builder is done
+enum Class {
+    Zone,
+    Any,
+    None,
+}
+
+struct Entry {
+    name: Name,
+    ty: Qtype,
+    class: Class,
+    ttl: u32,
+    // None for the records that stand for a whole RRset or name
+    rdata: Option<RData>,
+}
+
+impl UpdateBuilder {
+    pub fn new(zone: impl IntoName) -> UpdateBuilder {
+        let mut builder = UpdateBuilder {
+            id: 0,
+            zone: Name::root(),
+            class: Qclass::IN,
+            prerequisites: vec![],
+            updates: vec![],
+            signer: None,
+            error: None,
+        };
+        match zone.into_name() {
+            Ok(zone) => builder.zone = zone,
+            Err(e) => builder.fail(e),
+        }
+        builder
+    }
+
+    pub fn id(mut self, id: u16) -> Self {
+        self.id = id;
+        self
+    }
+
+    // The zone's class, IN unless set
+    pub fn class(mut self, class: Qclass) -> Self {
+        self.class = class;
+        self
+    }
+
+    // Prerequisites (RFC 2136 section 2.4)
+
+    // `name` has records of type `ty`, whatever they hold
+    pub fn rrset_exists(mut self, name: impl IntoName, ty: Qtype) -> Self {
+        self.prerequisite(name, ty, Class::Any);
+        self
+    }
+
+    // The RRset of `record` holds exactly the records given this way, each call adds one
+    pub fn rrset_exists_with(mut self, record: Answer) -> Self {
+        self.prerequisites.push(Entry::from_record(record, Class::Zone, 0));
+        self
+    }
+
+    // `name` has no records of type `ty`
+    pub fn rrset_absent(mut self, name: impl IntoName, ty: Qtype) -> Self {
+        self.prerequisite(name, ty, Class::None);
+        self
+    }
+
+    // `name` owns at least one record
+    pub fn name_in_use(mut self, name: impl IntoName) -> Self {
+        self.prerequisite(name, Qtype::ANY, Class::Any);
+        self
+    }
+
+    // `name` owns no records
+    pub fn name_absent(mut self, name: impl IntoName) -> Self {
+        self.prerequisite(name, Qtype::ANY, Class::None);
+        self
+    }
+
+    // Updates (RFC 2136 section 2.5)
+
+    // Adds `record` to its RRset, its class is replaced with the zone's
+    pub fn add_record(mut self, record: Answer) -> Self {
+        let ttl = record.ttl();
+        self.updates.push(Entry::from_record(record, Class::Zone, ttl));
+        self
+    }
+
+    // Deletes the record with the same owner, type and rdata as `record`
+    pub fn delete_record(mut self, record: Answer) -> Self {
+        self.updates.push(Entry::from_record(record, Class::None, 0));
+        self
+    }
+
+    // Deletes every record of type `ty` at `name`
+    pub fn delete_rrset(mut self, name: impl IntoName, ty: Qtype) -> Self {
+        self.update(name, ty);
+        self
+    }
+
+    // Deletes every record at `name`
+    pub fn delete_name(mut self, name: impl IntoName) -> Self {
+        self.update(name, Qtype::ANY);
+        self
+    }
+
+    // Signs the message when it is serialized, servers usually only take signed updates
+    pub fn tsig(mut self, signer: TsigSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    // Every name must be in the zone being updated
+    pub fn build(self) -> Result<Message, DnsError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.prerequisites.iter().chain(&self.updates).any(|e| !e.name.is_subdomain_of(&self.zone)) {
+            return Err(DnsError { kind: DnsErrorKind::OutsideZone, offset: 0 });
+        }
+        let mut builder = MessageBuilder::new()
+            .id(self.id)
+            .opcode(Opcode::Update)
+            .question(self.zone, Qtype::SOA, self.class);
+        for entry in self.prerequisites {
+            builder = builder.answer(entry.into_record(self.class));
+        }
+        for entry in self.updates {
+            builder = builder.authority(entry.into_record(self.class));
+        }
+        if let Some(signer) = self.signer {
+            builder = builder.tsig(signer);
+        }
+        builder.build()
+    }
+
+    fn prerequisite(&mut self, name: impl IntoName, ty: Qtype, class: Class) {
+        match name.into_name() {
+            Ok(name) => self.prerequisites.push(Entry { name, ty, class, ttl: 0, rdata: None }),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn update(&mut self, name: impl IntoName, ty: Qtype) {
+        match name.into_name() {
+            Ok(name) => self.updates.push(Entry { name, ty, class: Class::Any, ttl: 0, rdata: None }),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn fail(&mut self, e: DnsError) {
+        self.error.get_or_insert(e);
+    }
+}
+
+impl Entry {
+    fn from_record(record: Answer, class: Class, ttl: u32) -> Entry {
+        Entry { name: record.name().clone(), ty: record.ty(), class, ttl, rdata: Some(record.rdata().clone()) }
+    }
+
+    fn into_record(self, zone_class: Qclass) -> Answer {
+        let class = match self.class {
+            Class::Zone => zone_class,
+            Class::Any => Qclass::ANY,
+            Class::None => Qclass::NONE,
+        };
+        let rdata = self.rdata.unwrap_or(RData::Unknown { ty: u16::from(self.ty), data: vec![] });
+        Answer::new(self.name, class, self.ttl, rdata)
+    }
+}
+
+// The sections of an UPDATE message by the names RFC 2136 section 2 gives them
+impl Message {
+    // The zone being updated
+    pub fn zone(&self) -> Option<&Question> {
+        self.questions.first()
+    }
+
+    pub fn prerequisites(&self) -> &[Answer] {
+        &self.answers
+    }
+
+    pub fn updates(&self) -> &[Answer] {
+        &self.authority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::pkt::tsig::{TsigAlgorithm, TsigKey, TsigVerifier};
+
+    fn a(name: &str, ttl: u32, addr: [u8; 4]) -> Answer {
+        Answer::new(Name::from_ascii(name).unwrap(), Qclass::IN, ttl, RData::A(Ipv4Addr::from(addr)))
+    }
+
+    // (owner, type, class, ttl, whether the rdata is empty) of each record
+    fn entries(records: &[Answer]) -> Vec<(String, Qtype, Qclass, u32, bool)> {
+        records
+            .iter()
+            .map(|r| (r.name().to_string(), r.ty(), r.class(), r.ttl(), matches!(r.rdata(), RData::Unknown { data, .. } if data.is_empty())))
+            .collect()
+    }
+
+    #[test]
+    fn sections() {
+        let message = UpdateBuilder::new("example.")
+            .id(9)
+            .rrset_exists("www.example.", Qtype::A)
+            .rrset_exists_with(a("www.example.", 300, [192, 0, 2, 1]))
+            .rrset_absent("www.example.", Qtype::AAAA)
+            .name_in_use("example.")
+            .name_absent("new.example.")
+            .add_record(a("new.example.", 3600, [192, 0, 2, 2]))
+            .delete_record(a("www.example.", 300, [192, 0, 2, 1]))
+            .delete_rrset("old.example.", Qtype::A)
+            .delete_name("gone.example.")
+            .build()
+            .unwrap();
+        let parsed = Message::deserialize(&message.to_vec().unwrap()).unwrap();
+        assert_eq!(parsed, message);
+        assert_eq!(parsed.id(), 9);
+        assert_eq!(parsed.opcode(), Opcode::Update);
+
+        let zone = parsed.zone().unwrap();
+        assert_eq!(zone.qname().to_string(), "example.");
+        assert_eq!((zone.qtype(), zone.qclass()), (Qtype::SOA, Qclass::IN));
+
+        let name = |s: &str| s.to_string();
+        assert_eq!(entries(parsed.prerequisites()), [
+            (name("www.example."), Qtype::A, Qclass::ANY, 0, true),
+            (name("www.example."), Qtype::A, Qclass::IN, 0, false),
+            (name("www.example."), Qtype::AAAA, Qclass::NONE, 0, true),
+            (name("example."), Qtype::ANY, Qclass::ANY, 0, true),
+            (name("new.example."), Qtype::ANY, Qclass::NONE, 0, true),
+        ]);
+        assert_eq!(entries(parsed.updates()), [
+            (name("new.example."), Qtype::A, Qclass::IN, 3600, false),
+            (name("www.example."), Qtype::A, Qclass::NONE, 0, false),
+            (name("old.example."), Qtype::A, Qclass::ANY, 0, true),
+            (name("gone.example."), Qtype::ANY, Qclass::ANY, 0, true),
+        ]);
+        assert_eq!(parsed.updates()[0].rdata(), &RData::A(Ipv4Addr::new(192, 0, 2, 2)));
+    }
+
+    #[test]
+    fn zone_class() {
+        let message = UpdateBuilder::new("example.")
+            .class(Qclass::CH)
+            .add_record(a("www.example.", 60, [192, 0, 2, 1]))
+            .delete_record(a("www.example.", 60, [192, 0, 2, 3]))
+            .build()
+            .unwrap();
+        assert_eq!(message.zone().unwrap().qclass(), Qclass::CH);
+        let classes: Vec<_> = message.updates().iter().map(Answer::class).collect();
+        assert_eq!(classes, [Qclass::CH, Qclass::NONE]);
+    }
+
+    #[test]
+    fn outside_zone() {
+        let e = UpdateBuilder::new("example.").add_record(a("www.example.org.", 60, [192, 0, 2, 1])).build().unwrap_err();
+        assert_eq!(e.kind, DnsErrorKind::OutsideZone);
+        let e = UpdateBuilder::new("example.").rrset_absent("example.org.", Qtype::A).build().unwrap_err();
+        assert_eq!(e.kind, DnsErrorKind::OutsideZone);
+        assert!(UpdateBuilder::new("example.").delete_name("EXAMPLE.").build().is_ok());
+    }
+
+    #[test]
+    fn first_error_kept() {
+        let e = UpdateBuilder::new("example.")
+            .delete_name("a..example.")
+            .name_absent("b\\")
+            .build()
+            .unwrap_err();
+        assert_eq!(e.kind, DnsErrorKind::EmptyLabel);
+        assert!(UpdateBuilder::new("a..").build().is_err());
+    }
+
+    #[test]
+    fn signed() {
+        let key = || TsigKey::new("key.example.", TsigAlgorithm::HmacSha256, b"0123456789abcdef").unwrap();
+        let message = UpdateBuilder::new("example.")
+            .add_record(a("www.example.", 60, [192, 0, 2, 1]))
+            .tsig(TsigSigner::new(key()))
+            .build()
+            .unwrap();
+        let data = message.to_vec().unwrap();
+        let parsed = TsigVerifier::new(key()).verify(&data).unwrap();
+        assert_eq!(parsed.updates().len(), 1);
+        assert!(parsed.tsig().is_some());
+    }
+}
@@ -0,0 +1,413 @@
+use ring::digest;
+use ring::signature;
+use crate::pkt::answer::Answer;
+use crate::pkt::compress::Compressor;
+use crate::pkt::dnssec::{Algorithm, DigestType, Dnskey, Ds, Rrsig};
+use crate::pkt::error::{EncodeError, ValidationError};
+use crate::pkt::name::Name;
+use crate::pkt::question::Qtype;
+use crate::pkt::rdata::RData;
+
+// Checks that `rrsig` is a current signature over `rrset` by one of `dnskeys`, the signer's
+// DNSKEY RRset (RFC 4035 section 5.3). `now` is seconds since 1970
+pub fn verify_rrset(rrset: &[Answer], rrsig: &Rrsig, dnskeys: &[Answer], now: u32) -> Result<(), ValidationError> {
+    let data = signed_data(rrset, rrsig)?;
+    check_time(rrsig, now)?;
+    let mut result = Err(ValidationError::NoMatchingKey);
+    for key in matching_keys(rrsig, dnskeys) {
+        result = verify_signature(key, &data, &rrsig.signature);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+// Checks that `ds` is a digest of `key`, owned by `owner` (RFC 4035 section 5.2)
+pub fn verify_ds(ds: &Ds, owner: &Name, key: &Dnskey) -> Result<(), ValidationError> {
+    if !key.is_zone_key() || key.is_revoked() || ds.key_tag != key.key_tag() || ds.algorithm != key.algorithm {
+        return Err(ValidationError::DsMismatch);
+    }
+    if key.to_ds(owner, ds.digest_type)?.digest != ds.digest {
+        return Err(ValidationError::DsMismatch);
+    }
+    Ok(())
+}
+
+impl Dnskey {
+    // The DS record pointing at this key from the parent zone (RFC 4034 section 5.1.4)
+    pub fn to_ds(&self, owner: &Name, digest_type: DigestType) -> Result<Ds, ValidationError> {
+        let algorithm = match digest_type {
+            DigestType::SHA1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            DigestType::SHA256 => &digest::SHA256,
+            DigestType::SHA384 => &digest::SHA384,
+            ty => return Err(ValidationError::UnsupportedDigest(ty)),
+        };
+        let mut data = owner.to_lowercase().to_wire();
+        self.serialize(&mut data)?;
+        Ok(Ds {
+            key_tag: self.key_tag(),
+            algorithm: self.algorithm,
+            digest_type,
+            digest: digest::digest(algorithm, &data).as_ref().to_vec(),
+        })
+    }
+}
+
+// Fills in the signature of `rrsig` over `rrset`. `sign` is handed the data to sign and returns the
+// signature made with the private half of the key `rrsig` names
+pub fn sign_rrset(rrset: &[Answer], rrsig: Rrsig, sign: impl FnOnce(&[u8]) -> Vec<u8>) -> Result<Rrsig, ValidationError> {
+    let signature = sign(&signed_data(rrset, &rrsig)?);
+    Ok(Rrsig { signature, ..rrsig })
+}
+
+// What an RRSIG signs: its own rdata without the signature, then every record of the RRset in
+// canonical form and order with the RRSIG's original TTL (RFC 4034 section 3.1.8.1)
+pub(crate) fn signed_data(rrset: &[Answer], rrsig: &Rrsig) -> Result<Vec<u8>, ValidationError> {
+    let first = rrset.first().ok_or(ValidationError::NotAnRrset)?;
+    let owner = first.name();
+    if rrset.iter().any(|r| r.name() != owner || r.ty() != first.ty() || r.class() != first.class()) {
+        return Err(ValidationError::NotAnRrset);
+    }
+    // A leading `*` isn't counted, the signature was made over the wildcard
+    let labels = owner.label_count() - owner.is_wildcard() as usize;
+    if first.ty() != rrsig.type_covered || !owner.is_subdomain_of(&rrsig.signer_name) || rrsig.labels as usize > labels {
+        return Err(ValidationError::SignatureMismatch);
+    }
+    // An answer synthesized from a wildcard is checked against the wildcard (RFC 4035 section 5.3.2)
+    let owner = if (rrsig.labels as usize) < labels {
+        owner.suffix(owner.label_count() - rrsig.labels as usize).wildcard().to_lowercase()
+    } else {
+        owner.to_lowercase()
+    };
+    let mut data = vec![];
+    let unsigned = Rrsig { signer_name: rrsig.signer_name.to_lowercase(), signature: vec![], ..rrsig.clone() };
+    unsigned.serialize(&mut data)?;
+    let mut rdatas = rrset.iter().map(|r| canonical_rdata(r.rdata())).collect::<Result<Vec<_>, _>>()?;
+    rdatas.sort();
+    rdatas.dedup();
+    let owner = owner.to_wire();
+    for rdata in rdatas {
+        data.extend_from_slice(&owner);
+        data.extend_from_slice(&u16::from(first.ty()).to_be_bytes());
+        data.extend_from_slice(&u16::from(first.class()).to_be_bytes());
+        data.extend_from_slice(&rrsig.original_ttl.to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend_from_slice(&rdata);
+    }
+    Ok(data)
+}
+
+// Uncompressed, with the names inside the older types lowercased. NSEC keeps the case of its next
+// name (RFC 4034 section 6.2 as corrected by RFC 6840 section 5.1)
+fn canonical_rdata(rdata: &RData) -> Result<Vec<u8>, EncodeError> {
+    let lowered = match rdata {
+        RData::Ns(name) => RData::Ns(name.to_lowercase()),
+        RData::Cname(name) => RData::Cname(name.to_lowercase()),
+        RData::Ptr(name) => RData::Ptr(name.to_lowercase()),
+        RData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => RData::Soa {
+            mname: mname.to_lowercase(),
+            rname: rname.to_lowercase(),
+            serial: *serial,
+            refresh: *refresh,
+            retry: *retry,
+            expire: *expire,
+            minimum: *minimum,
+        },
+        RData::Mx { preference, exchange } => RData::Mx { preference: *preference, exchange: exchange.to_lowercase() },
+        RData::Srv { priority, weight, port, target } => RData::Srv {
+            priority: *priority,
+            weight: *weight,
+            port: *port,
+            target: target.to_lowercase(),
+        },
+        RData::Rrsig(rrsig) => RData::Rrsig(Rrsig { signer_name: rrsig.signer_name.to_lowercase(), ..rrsig.clone() }),
+        other => other.clone(),
+    };
+    let mut data = vec![];
+    lowered.serialize(&mut data, &mut Compressor::disabled())?;
+    Ok(data)
+}
+
+// Inception and expiration are compared with serial number arithmetic (RFC 1982), so the
+// check keeps working when the 32 bit times wrap
+fn check_time(rrsig: &Rrsig, now: u32) -> Result<(), ValidationError> {
+    let not_after = |a: u32, b: u32| b.wrapping_sub(a) < 1 << 31;
+    if !not_after(rrsig.inception, now) {
+        return Err(ValidationError::NotYetValid);
+    }
+    if !not_after(now, rrsig.expiration) {
+        return Err(ValidationError::Expired);
+    }
+    Ok(())
+}
+
+// Zone keys owned by the signer with the RRSIG's algorithm and key tag, tags can collide so
+// there may be more than one. Revoked keys must not be used (RFC 5011 section 2.1)
+fn matching_keys<'a>(rrsig: &'a Rrsig, dnskeys: &'a [Answer]) -> impl Iterator<Item = &'a Dnskey> {
+    dnskeys.iter()
+        .filter(|r| r.ty() == Qtype::DNSKEY && r.name() == &rrsig.signer_name)
+        .filter_map(|r| match r.rdata() {
+            RData::Dnskey(key) => Some(key),
+            _ => None,
+        })
+        .filter(|key| key.is_zone_key() && !key.is_revoked() && key.protocol == 3 && key.algorithm == rrsig.algorithm
+            && key.key_tag() == rrsig.key_tag)
+}
+
+// The algorithms `verify_signature` can check
+pub(crate) fn is_supported(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::RSASHA1 | Algorithm::RSASHA1NSEC3SHA1 | Algorithm::RSASHA256 | Algorithm::RSASHA512
+        | Algorithm::ECDSAP256SHA256 | Algorithm::ECDSAP384SHA384 | Algorithm::ED25519)
+}
+
+pub(crate) fn is_supported_digest(digest_type: DigestType) -> bool {
+    matches!(digest_type, DigestType::SHA1 | DigestType::SHA256 | DigestType::SHA384)
+}
+
+pub(crate) fn verify_signature(key: &Dnskey, data: &[u8], sig: &[u8]) -> Result<(), ValidationError> {
+    let result = match key.algorithm {
+        Algorithm::RSASHA1 | Algorithm::RSASHA1NSEC3SHA1 => {
+            verify_rsa(key, &signature::RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY, data, sig)?
+        }
+        Algorithm::RSASHA256 => verify_rsa(key, &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY, data, sig)?,
+        Algorithm::RSASHA512 => verify_rsa(key, &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY, data, sig)?,
+        Algorithm::ECDSAP256SHA256 => verify_ecdsa(key, &signature::ECDSA_P256_SHA256_FIXED, 64, data, sig)?,
+        Algorithm::ECDSAP384SHA384 => verify_ecdsa(key, &signature::ECDSA_P384_SHA384_FIXED, 96, data, sig)?,
+        Algorithm::ED25519 => {
+            if key.public_key.len() != 32 {
+                return Err(ValidationError::BadKey);
+            }
+            signature::UnparsedPublicKey::new(&signature::ED25519, &key.public_key).verify(data, sig)
+        }
+        alg => return Err(ValidationError::UnsupportedAlgorithm(alg)),
+    };
+    result.map_err(|_| ValidationError::BadSignature)
+}
+
+// The key is the exponent length in one byte, or zero and then two bytes, the exponent and
+// then the modulus (RFC 3110 section 2)
+fn verify_rsa(key: &Dnskey, params: &signature::RsaParameters, data: &[u8], sig: &[u8])
+              -> Result<Result<(), ring::error::Unspecified>, ValidationError> {
+    let key = key.public_key.as_slice();
+    let (len, rest) = match key {
+        [0, hi, lo, rest @ ..] => (u16::from_be_bytes([*hi, *lo]) as usize, rest),
+        [len, rest @ ..] => (*len as usize, rest),
+        [] => return Err(ValidationError::BadKey),
+    };
+    if len == 0 || rest.len() <= len {
+        return Err(ValidationError::BadKey);
+    }
+    let (e, n) = rest.split_at(len);
+    Ok(signature::RsaPublicKeyComponents { n, e }.verify(params, data, sig))
+}
+
+// DNSSEC keys are the bare point, ring wants the uncompressed SEC1 form with its 0x04 prefix
+fn verify_ecdsa(key: &Dnskey, alg: &'static signature::EcdsaVerificationAlgorithm, len: usize, data: &[u8], sig: &[u8])
+                -> Result<Result<(), ring::error::Unspecified>, ValidationError> {
+    if key.public_key.len() != len {
+        return Err(ValidationError::BadKey);
+    }
+    let mut point = Vec::with_capacity(len + 1);
+    point.push(0x04);
+    point.extend_from_slice(&key.public_key);
+    Ok(signature::UnparsedPublicKey::new(alg, &point).verify(data, sig))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use crate::pkt::dnssec::{REVOKE, SECURE_ENTRY_POINT, ZONE_KEY};
+    use crate::pkt::question::Qclass;
+
+    // RFC 8080 section 6.1, the first example
+    const ED25519_KEY: &str = "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=";
+    const ED25519_MX: &str = "example.com. 3600 IN MX 10 mail.example.com.";
+    const ED25519_SIG: &str = "example.com. 3600 IN RRSIG MX 15 2 3600 1440021600 1438207200 3613 example.com. \
+        oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg==";
+    const AUG_2015: u32 = 1439000000;
+
+    fn record(s: &str) -> Answer {
+        s.parse().unwrap()
+    }
+
+    fn dnskey(record: &Answer) -> &Dnskey {
+        match record.rdata() {
+            RData::Dnskey(key) => key,
+            _ => panic!("not a DNSKEY"),
+        }
+    }
+
+    fn rrsig(s: &str) -> Rrsig {
+        match record(s).rdata() {
+            RData::Rrsig(rrsig) => rrsig.clone(),
+            _ => panic!("not an RRSIG"),
+        }
+    }
+
+    fn ds(s: &str) -> Ds {
+        match record(s).rdata() {
+            RData::Ds(ds) => ds.clone(),
+            _ => panic!("not a DS"),
+        }
+    }
+
+    fn verify(rrset: &[&str], rrsig: &Rrsig, key: &str, now: u32) -> Result<(), ValidationError> {
+        let rrset: Vec<Answer> = rrset.iter().map(|s| record(s)).collect();
+        verify_rrset(&rrset, rrsig, &[record(key)], now)
+    }
+
+    // An Ed25519 key of our own for the cases the RFCs have no example of
+    fn signing_key(flags: u16) -> (Ed25519KeyPair, Answer) {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let key = Dnskey { flags, protocol: 3, algorithm: Algorithm::ED25519, public_key: pair.public_key().as_ref().to_vec() };
+        (pair, Answer::new(Name::from_ascii("example.com.").unwrap(), Qclass::IN, 3600, RData::Dnskey(key)))
+    }
+
+    fn sign(pair: &Ed25519KeyPair, key: &Answer, rrset: &[&str], labels: u8) -> Rrsig {
+        let rrsig = Rrsig {
+            type_covered: record(rrset[0]).ty(),
+            algorithm: Algorithm::ED25519,
+            labels,
+            original_ttl: 3600,
+            expiration: AUG_2015 + 86400,
+            inception: AUG_2015 - 86400,
+            key_tag: dnskey(key).key_tag(),
+            signer_name: key.name().clone(),
+            signature: vec![],
+        };
+        resign(pair, rrset, rrsig)
+    }
+
+    // Signs `rrset` over again with the other fields of `rrsig`
+    fn resign(pair: &Ed25519KeyPair, rrset: &[&str], rrsig: Rrsig) -> Rrsig {
+        let rrset: Vec<Answer> = rrset.iter().map(|s| record(s)).collect();
+        sign_rrset(&rrset, rrsig, |data| pair.sign(data).as_ref().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn ed25519() {
+        let key = record(ED25519_KEY);
+        let ds = ds("example.com. 3600 IN DS 3613 15 2 3aa5ab37efce57f737fc1627013fee07bdf241bd10f3b1964ab55c78e79a304b");
+        assert_eq!(verify_ds(&ds, key.name(), dnskey(&key)), Ok(()));
+        assert_eq!(verify(&[ED25519_MX], &rrsig(ED25519_SIG), ED25519_KEY, AUG_2015), Ok(()));
+        // The second example
+        let key = "example.com. 3600 IN DNSKEY 257 3 15 zPnZ/QwEe7S8C5SPz2OfS5RR40ATk2/rYnE9xHIEijs=";
+        let sig = rrsig("example.com. 3600 IN RRSIG MX 15 2 3600 1440021600 1438207200 35217 example.com. \
+            zXQ0bkYgQTEFyfLyi9QoiY6D8ZdYo4wyUhVioYZXFdT410QPRITQSqJSnzQoSm5poJ7gD7AQR0O7KuI5k2pcBg==");
+        assert_eq!(verify(&[ED25519_MX], &sig, key, AUG_2015), Ok(()));
+        let other = record("example.com. 3600 IN MX 20 mail.example.com.");
+        assert_eq!(verify_rrset(&[other], &sig, &[record(key)], AUG_2015), Err(ValidationError::BadSignature));
+    }
+
+    #[test]
+    fn rfc4034_ds() {
+        // RFC 4034 section 5.4
+        let key = record("dskey.example.com. 86400 IN DNSKEY 256 3 5 AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/\
+            2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==");
+        let ds = ds("dskey.example.com. 86400 IN DS 60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118");
+        assert_eq!(dnskey(&key).to_ds(key.name(), DigestType::SHA1), Ok(ds.clone()));
+        assert_eq!(verify_ds(&ds, &Name::from_ascii("DSKEY.example.com.").unwrap(), dnskey(&key)), Ok(()));
+        assert_eq!(verify_ds(&ds, &Name::from_ascii("other.example.com.").unwrap(), dnskey(&key)),
+                   Err(ValidationError::DsMismatch));
+    }
+
+    #[test]
+    fn rsa_sha256() {
+        // RFC 5702 section 6.1. The key is 512 bits, below the 1024 ring accepts, so the signed
+        // data is checked against the SHA-256 digest inside the example's signature instead
+        let key = record("example.net. 3600 IN DNSKEY 256 3 8 AwEAAcFcGsaxxdgiuuGmCkVImy4h99CqT7jwY3pexPGcnUFtR2Fh36Bp\
+            oncwtkZ4cAgtvd4Qs8PkxUdp6p/DlUmObdk=");
+        assert_eq!(dnskey(&key).key_tag(), 9033);
+        let sig = rrsig("www.example.net. 3600 IN RRSIG A 8 3 3600 20300101000000 20000101000000 9033 example.net. \
+            kRCOH6u7l0QGy9qpC9l1sLncJcOKFLJ7GhiUOibu4teYp5VE9RncriShZNz85mwlMgNEacFYK/lPtPiVYP4bwg==");
+        let data = signed_data(&[record("www.example.net. 3600 IN A 192.0.2.91")], &sig).unwrap();
+        assert_eq!(data_encoding::HEXLOWER.encode(digest::digest(&digest::SHA256, &data).as_ref()),
+                   "1f3cb0b430354865876f3a70a5d34ed10a4346fd67869aca7b824bf9218d90cc");
+        // Section 6.2, RSA/SHA-512 with a 1024 bit key
+        let key = "example.net. 3600 IN DNSKEY 256 3 10 AwEAAdHoNTOW+et86KuJOWRDp1pndvwb6Y83nSVXXyLA3DLroROUkN6X0O6p\
+            nWnjJQujX/AyhqFDxj13tOnD9u/1kTg7cV6rklMrZDtJCQ5PCl/D7QNPsgVsMu1J2Q8gpMpztNFLpPBz1bWXjDtaR7ZQBlZ3PFY12ZTSncorffcGmhOL";
+        let sig = rrsig("www.example.net. 3600 IN RRSIG A 10 3 3600 20300101000000 20000101000000 3740 example.net. \
+            tsb4wnjRUDnB1BUi+t6TMTXThjVnG+eCkWqjvvjhzQL1d0YRoOe0CbxrVDYd0xDtsuJRaeUw1ep94PzEWzr0iGYgZBWm/zpq+9fOuagY\
+            JRfDqfReKBzMweOLDiNa8iP5g9vMhpuv6OPlvpXwm9Sa9ZXIbNl1MBGk0fthPgxdDLw=");
+        assert_eq!(verify(&["www.example.net. 3600 IN A 192.0.2.91"], &sig, key, 1500000000), Ok(()));
+    }
+
+    #[test]
+    fn ecdsa_p256() {
+        // RFC 6605 section 6.1
+        let key = "example.net. 3600 IN DNSKEY 257 3 13 GojIhhXUN/u4v54ZQqGSnyhWJwaubCvTmeexv7bR6edbkrSqQpF64cYbcB7wNcP+\
+            e+MAnLr+Wi9xMWyQLc8NAA==";
+        let ds = ds("example.net. 3600 IN DS 55648 13 2 b4c8c1fe2e7477127b27115656ad6256f424625bf5c1e2770ce6d6e37df61d17");
+        assert_eq!(verify_ds(&ds, record(key).name(), dnskey(&record(key))), Ok(()));
+        let sig = rrsig("www.example.net. 3600 IN RRSIG A 13 3 3600 20100909100439 20100812100439 55648 example.net. \
+            qx6wLYqmh+l9oCKTN6qIc+bw6ya+KJ8oMz0YP107epXAyGmt+3SNruPFKG7tZoLBLlUzGGus7ZwmwWep666VCw==");
+        assert_eq!(verify(&["www.example.net. 3600 IN A 192.0.2.1"], &sig, key, 1282000000), Ok(()));
+    }
+
+    #[test]
+    fn validity_period() {
+        let sig = rrsig(ED25519_SIG);
+        let mx = [ED25519_MX];
+        assert_eq!(verify(&mx, &sig, ED25519_KEY, sig.inception), Ok(()));
+        assert_eq!(verify(&mx, &sig, ED25519_KEY, sig.expiration), Ok(()));
+        assert_eq!(verify(&mx, &sig, ED25519_KEY, sig.inception - 1), Err(ValidationError::NotYetValid));
+        assert_eq!(verify(&mx, &sig, ED25519_KEY, sig.expiration + 1), Err(ValidationError::Expired));
+        // Across the wrap in 2106 the times still compare by serial number arithmetic
+        let (pair, key) = signing_key(ZONE_KEY);
+        let wrapped = Rrsig { inception: u32::MAX - 10, expiration: 10, ..sign(&pair, &key, &mx, 2) };
+        let wrapped = resign(&pair, &mx, wrapped);
+        let keys = std::slice::from_ref(&key);
+        for now in [u32::MAX - 10, u32::MAX, 0, 10] {
+            assert_eq!(verify_rrset(&[record(ED25519_MX)], &wrapped, keys, now), Ok(()), "{}", now);
+        }
+        assert_eq!(verify_rrset(&[record(ED25519_MX)], &wrapped, keys, 11), Err(ValidationError::Expired));
+        assert_eq!(verify_rrset(&[record(ED25519_MX)], &wrapped, keys, u32::MAX - 11), Err(ValidationError::NotYetValid));
+    }
+
+    #[test]
+    fn uppercase_owner() {
+        // Owner and MX target are lowercased before checking
+        let upper = ["EXAMPLE.COM. 3600 IN MX 10 Mail.Example.COM."];
+        assert_eq!(verify(&upper, &rrsig(ED25519_SIG), ED25519_KEY, AUG_2015), Ok(()));
+        let mut sig = rrsig(ED25519_SIG);
+        sig.signer_name = Name::from_ascii("Example.Com.").unwrap();
+        assert_eq!(verify(&upper, &sig, ED25519_KEY, AUG_2015), Ok(()));
+    }
+
+    #[test]
+    fn wildcard_labels() {
+        let (pair, key) = signing_key(ZONE_KEY);
+        let sig = sign(&pair, &key, &["*.example.com. 3600 IN MX 10 mail.example.com."], 2);
+        // The expansion to any name below the wildcard's parent verifies
+        for owner in ["a.example.com.", "b.a.example.com.", "*.example.com."] {
+            let rrset = [record(&format!("{} 3600 IN MX 10 mail.example.com.", owner))];
+            assert_eq!(verify_rrset(&rrset, &sig, std::slice::from_ref(&key), AUG_2015), Ok(()));
+        }
+        // Labels past the owner's count
+        let sig = Rrsig { labels: 3, ..sig };
+        assert_eq!(verify_rrset(&[record(ED25519_MX)], &sig, std::slice::from_ref(&key), AUG_2015),
+                   Err(ValidationError::SignatureMismatch));
+        // A wildcard owner with the star counted is refused as well
+        let rrset = [record("*.example.com. 3600 IN MX 10 mail.example.com.")];
+        assert_eq!(verify_rrset(&rrset, &sig, &[key], AUG_2015), Err(ValidationError::SignatureMismatch));
+    }
+
+    #[test]
+    fn key_flags() {
+        let mx = [ED25519_MX];
+        let (pair, key) = signing_key(ZONE_KEY | SECURE_ENTRY_POINT);
+        let sig = sign(&pair, &key, &mx, 2);
+        assert_eq!(verify_rrset(&[record(ED25519_MX)], &sig, &[key], AUG_2015), Ok(()));
+        // The same key without the zone key bit, or revoked, signing with its own tag
+        for flags in [SECURE_ENTRY_POINT, ZONE_KEY | REVOKE | SECURE_ENTRY_POINT] {
+            let (pair, key) = signing_key(flags);
+            let sig = sign(&pair, &key, &mx, 2);
+            assert_eq!(verify_rrset(&[record(ED25519_MX)], &sig, std::slice::from_ref(&key), AUG_2015),
+                       Err(ValidationError::NoMatchingKey));
+            let ds = dnskey(&key).to_ds(key.name(), DigestType::SHA256).unwrap();
+            assert_eq!(verify_ds(&ds, key.name(), dnskey(&key)), Err(ValidationError::DsMismatch));
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::pkt::answer::Answer;
+use crate::pkt::error::DnsErrorKind;
+use crate::pkt::name::Name;
+use crate::pkt::text::{parse_record, parse_ttl, tokenize, Defaults};
+
+// How deep $INCLUDE may nest, also what stops a file including itself forever
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum ZoneErrorKind {
+    Io(io::Error),
+    // A record or directive that couldn't be read
+    Syntax(DnsErrorKind),
+    UnknownDirective(String),
+    // A `)` with no `(` before it, or a file ending inside parentheses
+    UnbalancedParentheses,
+    IncludeTooDeep,
+}
+
+// Where in which file loading a zone failed, lines count from 1
+#[derive(Debug)]
+pub struct ZoneError {
+    pub file: PathBuf,
+    pub line: usize,
+    pub kind: ZoneErrorKind,
+}
+
+impl fmt::Display for ZoneErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ZoneErrorKind::Io(e) => write!(f, "{}", e),
+            ZoneErrorKind::Syntax(kind) => write!(f, "{}", kind),
+            ZoneErrorKind::UnknownDirective(directive) => write!(f, "unknown directive {}", directive),
+            ZoneErrorKind::UnbalancedParentheses => write!(f, "unbalanced parentheses"),
+            ZoneErrorKind::IncludeTooDeep => write!(f, "$INCLUDE nested more than {} deep", MAX_INCLUDE_DEPTH),
+        }
+    }
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // A file that couldn't be read has no line to point at
+        if self.line == 0 {
+            return write!(f, "{}: {}", self.file.display(), self.kind);
+        }
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.kind)
+    }
+}
+
+impl Error for ZoneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ZoneErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Loads an RFC 1035 master file (section 5). Relative names are completed with `origin` until
+// a $ORIGIN changes it, and $INCLUDE paths are taken relative to the including file
+pub fn load_zone(path: impl AsRef<Path>, origin: &Name) -> Result<Vec<Answer>, ZoneError> {
+    let mut records = vec![];
+    let mut state = State { defaults: Defaults { origin: origin.clone(), ..Defaults::new() }, ttl: None };
+    read_file(path.as_ref(), &mut state, &mut records, 0)?;
+    Ok(records)
+}
+
+// Like `load_zone` for text already in memory, `file` is only used in errors and to find includes
+pub fn parse_zone(text: &str, file: impl AsRef<Path>, origin: &Name) -> Result<Vec<Answer>, ZoneError> {
+    let mut records = vec![];
+    let mut state = State { defaults: Defaults { origin: origin.clone(), ..Defaults::new() }, ttl: None };
+    parse_text(text, file.as_ref(), &mut state, &mut records, 0)?;
+    Ok(records)
+}
+
+struct State {
+    defaults: Defaults,
+    // The $TTL, when set it is used in place of the last record's TTL (RFC 2308 section 4)
+    ttl: Option<u32>,
+}
+
+fn read_file(path: &Path, state: &mut State, records: &mut Vec<Answer>, depth: usize) -> Result<(), ZoneError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| ZoneError { file: path.to_path_buf(), line: 0, kind: ZoneErrorKind::Io(e) })?;
+    parse_text(&text, path, state, records, depth)
+}
+
+fn parse_text(text: &str, file: &Path, state: &mut State, records: &mut Vec<Answer>, depth: usize) -> Result<(), ZoneError> {
+    let fail = |line, kind| Err(ZoneError { file: file.to_path_buf(), line, kind });
+    let mut entry = String::new();
+    let mut start = 0;
+    let mut parens = 0;
+    for (i, line) in text.lines().enumerate() {
+        if entry.is_empty() {
+            start = i + 1;
+        } else {
+            entry.push('\n');
+        }
+        entry.push_str(line);
+        parens += match count_parens(line) {
+            Some(n) => n,
+            None => return fail(i + 1, ZoneErrorKind::Syntax(DnsErrorKind::UnterminatedQuote)),
+        };
+        if parens < 0 {
+            return fail(i + 1, ZoneErrorKind::UnbalancedParentheses);
+        }
+        if parens > 0 {
+            continue;
+        }
+        parse_entry(&entry, file, start, state, records, depth)?;
+        entry.clear();
+    }
+    if parens > 0 {
+        return fail(start, ZoneErrorKind::UnbalancedParentheses);
+    }
+    Ok(())
+}
+
+// How much `line` changes the parenthesis depth, parentheses in quotes, escapes and comments
+// don't count. Quoted strings can't run on to the next line
+fn count_parens(line: &str) -> Option<i32> {
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut bytes = line.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\\' => {
+                bytes.next();
+            }
+            b'"' => quoted = !quoted,
+            b';' if !quoted => break,
+            b'(' if !quoted => depth += 1,
+            b')' if !quoted => depth -= 1,
+            _ => {}
+        }
+    }
+    if quoted {
+        return None;
+    }
+    Some(depth)
+}
+
+fn parse_entry(entry: &str, file: &Path, start: usize, state: &mut State, records: &mut Vec<Answer>,
+               depth: usize) -> Result<(), ZoneError> {
+    // Errors carry an offset into the entry, which may span several lines
+    let error = |offset: usize, kind| {
+        let line = start + entry[..offset.min(entry.len())].matches('\n').count();
+        ZoneError { file: file.to_path_buf(), line, kind }
+    };
+    let tokens = match tokenize(entry) {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(error(e.offset, ZoneErrorKind::Syntax(e.kind))),
+    };
+    let first = match tokens.first() {
+        Some(t) => *t,
+        None => return Ok(()),
+    };
+    if first.offset != 0 || !first.text.starts_with('$') {
+        state.defaults.ttl = state.ttl.or(state.defaults.ttl);
+        let record = match parse_record(entry, &state.defaults) {
+            Ok(record) => record,
+            Err(e) => return Err(error(e.offset, ZoneErrorKind::Syntax(e.kind))),
+        };
+        // Left out fields repeat the last ones given (RFC 1035 section 5.1)
+        state.defaults.owner = Some(record.name().clone());
+        state.defaults.class = record.class();
+        state.defaults.ttl = Some(record.ttl());
+        records.push(record);
+        return Ok(());
+    }
+    let arg = |i: usize, what| match tokens.get(i) {
+        Some(t) => Ok(*t),
+        None => Err(error(entry.len(), ZoneErrorKind::Syntax(DnsErrorKind::MissingField(what)))),
+    };
+    let extra = |n: usize| match tokens.get(n) {
+        Some(t) => Err(error(t.offset, ZoneErrorKind::Syntax(DnsErrorKind::ExtraField))),
+        None => Ok(()),
+    };
+    let name = |i: usize, what| {
+        let t = arg(i, what)?;
+        Name::from_ascii_in(t.text, &state.defaults.origin)
+            .map_err(|e| error(t.offset + e.offset, ZoneErrorKind::Syntax(e.kind)))
+    };
+    match first.text.to_ascii_uppercase().as_str() {
+        "$ORIGIN" => {
+            let origin = name(1, "origin")?;
+            extra(2)?;
+            state.defaults.origin = origin;
+        }
+        "$TTL" => {
+            let t = arg(1, "TTL")?;
+            extra(2)?;
+            match parse_ttl(t.text) {
+                Some(ttl) => state.ttl = Some(ttl),
+                None => return Err(error(t.offset, ZoneErrorKind::Syntax(DnsErrorKind::BadField("TTL")))),
+            }
+        }
+        // The included file starts from the parent's state and changes none of it (section 5.1)
+        "$INCLUDE" => {
+            let path = arg(1, "file name")?;
+            let origin = match tokens.get(2) {
+                Some(_) => name(2, "origin")?,
+                None => state.defaults.origin.clone(),
+            };
+            extra(3)?;
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(error(first.offset, ZoneErrorKind::IncludeTooDeep));
+            }
+            let path = file.parent().unwrap_or(Path::new("")).join(path.text);
+            let mut included = State { defaults: Defaults { origin, ..state.defaults.clone() }, ttl: state.ttl };
+            read_file(&path, &mut included, records, depth + 1)?;
+        }
+        _ => return Err(error(first.offset, ZoneErrorKind::UnknownDirective(first.text.to_string()))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkt::question::{Qclass, Qtype};
+
+    const ZONE: &str = "\
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns1 hostmaster (
+            2024010101 ; serial
+            7200 3600 1209600 300 )
+    IN  NS  ns1
+ns1 600 A   192.0.2.1
+    AAAA    2001:db8::1 ; same owner and TTL
+$ORIGIN sub.example.com.
+www CNAME   @
+";
+
+    fn origin() -> Name {
+        Name::from_ascii("example.com.").unwrap()
+    }
+
+    #[test]
+    fn directives_and_defaults() {
+        let records = parse_zone(ZONE, "example.com.zone", &origin()).unwrap();
+        let text: Vec<String> = records.iter().map(|r| r.to_string()).collect();
+        assert_eq!(text, [
+            "example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. 2024010101 7200 3600 1209600 300\n",
+            "example.com.\t3600\tIN\tNS\tns1.example.com.\n",
+            "ns1.example.com.\t600\tIN\tA\t192.0.2.1\n",
+            "ns1.example.com.\t3600\tIN\tAAAA\t2001:db8::1\n",
+            "www.sub.example.com.\t3600\tIN\tCNAME\tsub.example.com.\n",
+        ]);
+    }
+
+    #[test]
+    fn errors_carry_the_line() {
+        let e = parse_zone("@ 60 IN A 192.0.2.1\n@ 60 IN A nope\n", "z", &origin()).unwrap_err();
+        assert_eq!((e.line, e.to_string().as_str()), (2, "z:2: invalid address"));
+        let e = parse_zone("@ 60 IN SOA a b (\n1 2 3 4 5\n", "z", &origin()).unwrap_err();
+        assert!(matches!((e.line, e.kind), (1, ZoneErrorKind::UnbalancedParentheses)));
+        let e = parse_zone("$GENERATE 1-2 a A 1.2.3.4\n", "z", &origin()).unwrap_err();
+        assert!(matches!(e.kind, ZoneErrorKind::UnknownDirective(d) if d == "$GENERATE"));
+        let e = parse_zone("@ IN A 192.0.2.1\n", "z", &origin()).unwrap_err();
+        assert!(matches!(e.kind, ZoneErrorKind::Syntax(DnsErrorKind::MissingField("TTL"))));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("dns-zone-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hosts"), "www 60 A 192.0.2.2\n").unwrap();
+        fs::write(dir.join("main.zone"), "$INCLUDE hosts sub\nmail 60 MX 10 www.sub\n").unwrap();
+        fs::write(dir.join("loop.zone"), "$INCLUDE loop.zone\n").unwrap();
+        let records = load_zone(dir.join("main.zone"), &origin()).unwrap();
+        assert_eq!(records[0].name(), &Name::from_ascii("www.sub.example.com.").unwrap());
+        assert_eq!((records[0].ty(), records[0].class()), (Qtype::A, Qclass::IN));
+        // The origin set by $INCLUDE only lasts until the end of the included file
+        assert_eq!(records[1].name(), &Name::from_ascii("mail.example.com.").unwrap());
+        let e = load_zone(dir.join("loop.zone"), &origin()).unwrap_err();
+        assert!(matches!(e.kind, ZoneErrorKind::IncludeTooDeep));
+        let e = load_zone(dir.join("missing.zone"), &origin()).unwrap_err();
+        assert!(matches!((e.line, e.kind), (0, ZoneErrorKind::Io(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
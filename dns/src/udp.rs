@@ -1,7 +1,7 @@
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::net::UdpSocket;
-use crate::Message;
+use dns::{DnsError, Message};
 
 
 pub fn init_conn(addr: &str) -> UdpSocket {
@@ -10,10 +10,10 @@ pub fn init_conn(addr: &str) -> UdpSocket {
 }
 
 
-pub fn send_dns_q(data : &Vec<u8>) -> Message {
+pub fn send_dns_q(data : &[u8]) -> Result<Message, DnsError> {
     let conn = init_conn("0.0.0.0:8080");
     println!("Sending dns request");
-    conn.send_to( data, &"1.1.1.1:53").expect("unable to send pkt");
+    conn.send_to( data, "1.1.1.1:53").expect("unable to send pkt");
     println!("sent request");
 
     let mut buf = [0; 10000];
@@ -22,6 +22,7 @@ pub fn send_dns_q(data : &Vec<u8>) -> Message {
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open("raw_pkt")
         .expect("unable to write to file");
 
@@ -0,0 +1,37 @@
+// Uses the crate the way a dependent service would, through the re-exports at the root
+use std::net::Ipv4Addr;
+use dns::{Message, Opcode, Qclass, Qtype, RData, Rcode};
+
+const RAW: &[u8] = include_bytes!("../raw_pkt");
+
+#[test]
+fn accessors() {
+    let message = Message::deserialize(RAW).unwrap();
+    let header = message.header();
+    assert_eq!(header.id(), 0x0539);
+    assert_eq!(header.opcode(), Opcode::Query);
+    assert_eq!(message.rcode(), Rcode::NoError);
+    assert_eq!((header.qdcount(), header.ancount(), header.nscount(), header.arcount()), (1, 4, 0, 0));
+    assert_eq!(message.flags().to_string(), "qr rd ra");
+
+    let question = &message.questions()[0];
+    assert_eq!(question.qname().to_string(), "www.northeastern.edu.");
+    assert_eq!((question.qtype(), question.qclass()), (Qtype::A, Qclass::IN));
+
+    let addresses: Vec<&RData> = message.records_of_type(Qtype::A).map(|r| r.rdata()).collect();
+    assert_eq!(addresses, [&RData::A(Ipv4Addr::new(23, 47, 188, 88)), &RData::A(Ipv4Addr::new(23, 47, 188, 104))]);
+    assert_eq!(message.records_of_type(Qtype::CNAME).count(), 2);
+    assert!(message.authority().is_empty() && message.additional().is_empty());
+}
+
+#[test]
+fn set_id_and_flags() {
+    let mut message = Message::deserialize(RAW).unwrap();
+    let mut flags = message.flags();
+    flags.aa = true;
+    message.set_flags(flags);
+    message.set_id(1);
+    let parsed = Message::deserialize(&message.to_vec().unwrap()).unwrap();
+    assert_eq!(parsed.id(), 1);
+    assert!(parsed.flags().aa);
+}
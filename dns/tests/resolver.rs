@@ -0,0 +1,467 @@
+// Resolves through stand-in root, TLD and leaf servers. Each listens on its own port of 127.0.0.1
+// and the resolver is told to ask there for the glue addresses the zones give, and each signed zone
+// has its own key
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+use data_encoding::BASE32HEX_NOPAD;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use dns::*;
+
+const NOW: u32 = 1_700_000_000;
+const TLD: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 2);
+const LEAF: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 3);
+
+const ROOT: &str = "
+$TTL 3600
+@        SOA  a.root. nstld.root. 1 1800 900 604800 86400
+@        NS   a.root.
+test.    NS   ns.test.
+ns.test. A    198.51.100.2
+plain.   NS   ns.plain.
+ns.plain. A   198.51.100.3
+";
+
+const TEST: &str = "
+$TTL 3600
+@        SOA  ns admin 1 1800 900 604800 3600
+@        NS   ns
+ns       A    198.51.100.2
+signed   NS   ns.signed
+ns.signed A   198.51.100.3
+broken   NS   ns.broken
+ns.broken A   198.51.100.3
+unsigned NS   ns.unsigned
+ns.unsigned A 198.51.100.3
+gost     NS   ns.gost
+ns.gost  A    198.51.100.3
+";
+
+const SIGNED: &str = "
+$TTL 3600
+@        SOA   ns admin 1 1800 900 604800 3600
+@        NS    ns
+ns       A     198.51.100.3
+www      A     192.0.2.1
+forged   A     192.0.2.2
+alias    CNAME www
+away     CNAME www.unsigned.test.
+";
+
+const BROKEN: &str = "
+$TTL 3600
+@        SOA  ns admin 1 1800 900 604800 3600
+@        NS   ns
+www      A    192.0.2.3
+";
+
+const UNSIGNED: &str = "
+$TTL 3600
+@        SOA  ns admin 1 1800 900 604800 3600
+@        NS   ns
+www      A    192.0.2.4
+";
+
+const GOST: &str = "
+$TTL 3600
+@        SOA  ns admin 1 1800 900 604800 3600
+@        NS   ns
+www      A    192.0.2.6
+";
+
+const PLAIN: &str = "
+$TTL 3600
+@        SOA  ns admin 1 1800 900 604800 3600
+@        NS   ns
+www      A    192.0.2.5
+";
+
+enum Chain {
+    Nsec,
+    Nsec3,
+}
+
+struct Zone {
+    apex: Name,
+    records: Vec<Answer>,
+    key: Option<(Ed25519KeyPair, Answer)>,
+}
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+impl Zone {
+    fn new(apex: &str, text: &str) -> Zone {
+        let apex = name(apex);
+        let records = parse_zone(text, "zone", &apex).unwrap();
+        Zone { apex, records, key: None }
+    }
+
+    // Adds a key made from `seed`, then the NSEC or NSEC3 chain, then signs every RRset
+    fn signed(mut self, seed: u8, chain: Chain) -> Zone {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap();
+        let key = Dnskey { flags: 257, protocol: 3, algorithm: Algorithm::ED25519, public_key: pair.public_key().as_ref().to_vec() };
+        let key = Answer::new(self.apex.clone(), Qclass::IN, 3600, RData::Dnskey(key));
+        self.records.push(key.clone());
+        self.key = Some((pair, key));
+        match chain {
+            Chain::Nsec => self.add_nsec(),
+            Chain::Nsec3 => self.add_nsec3(),
+        }
+        self.sign();
+        self
+    }
+
+    // The DS for this zone's key, as its parent would publish it
+    fn ds(&self) -> Answer {
+        let (_, key) = self.key.as_ref().unwrap();
+        let RData::Dnskey(dnskey) = key.rdata() else { unreachable!() };
+        let ds = dnskey.to_ds(&self.apex, DigestType::SHA256).unwrap();
+        Answer::new(self.apex.clone(), Qclass::IN, 3600, RData::Ds(ds))
+    }
+
+    // A delegation point other than the apex at or above `name`
+    fn cut(&self, name: &Name) -> Option<Name> {
+        self.records.iter()
+            .find(|r| r.ty() == Qtype::NS && r.name() != &self.apex && name.is_subdomain_of(r.name()))
+            .map(|r| r.name().clone())
+    }
+
+    // Glue and the NS records at a cut belong to the child, the zone doesn't sign them
+    fn is_authoritative(&self, record: &Answer) -> bool {
+        match self.cut(record.name()) {
+            Some(cut) => cut == *record.name() && record.ty() != Qtype::NS,
+            None => true,
+        }
+    }
+
+    // Each name the zone holds data or a delegation for, with its types
+    fn names(&self) -> Vec<(Name, Vec<Qtype>)> {
+        let mut names: Vec<(Name, Vec<Qtype>)> = vec![];
+        for r in self.records.iter().filter(|r| self.cut(r.name()).is_none_or(|cut| cut == *r.name())) {
+            match names.iter_mut().find(|(n, _)| n == r.name()) {
+                Some((_, types)) => types.push(r.ty()),
+                None => names.push((r.name().clone(), vec![r.ty()])),
+            }
+        }
+        for (n, types) in names.iter_mut() {
+            if self.cut(n).is_none() || types.contains(&Qtype::DS) {
+                types.push(Qtype::RRSIG);
+            }
+        }
+        names
+    }
+
+    fn add_nsec(&mut self) {
+        let mut names = self.names();
+        names.sort_by(|a, b| a.0.canonical_cmp(&b.0));
+        for i in 0..names.len() {
+            let (owner, types) = &names[i];
+            let next_name = names[(i + 1) % names.len()].0.clone();
+            let types = TypeBitmap::new(types.iter().copied().chain([Qtype::RRSIG, Qtype::NSEC]));
+            self.records.push(Answer::new(owner.clone(), Qclass::IN, 3600, RData::Nsec(Nsec { next_name, types })));
+        }
+    }
+
+    fn add_nsec3(&mut self) {
+        let mut hashed: Vec<(Vec<u8>, Vec<Qtype>)> = self.names().into_iter()
+            .map(|(n, types)| (nsec3_hash(&n, &[], 0), types))
+            .collect();
+        hashed.sort_by(|a, b| a.0.cmp(&b.0));
+        for i in 0..hashed.len() {
+            let (hash, types) = &hashed[i];
+            let owner = Name::from_ascii_in(&BASE32HEX_NOPAD.encode(hash), &self.apex).unwrap();
+            let nsec3 = Nsec3 {
+                hash_algorithm: 1,
+                flags: 0,
+                iterations: 0,
+                salt: vec![],
+                next_hashed: hashed[(i + 1) % hashed.len()].0.clone(),
+                types: TypeBitmap::new(types.iter().copied()),
+            };
+            self.records.push(Answer::new(owner, Qclass::IN, 3600, RData::Nsec3(nsec3)));
+        }
+    }
+
+    fn sign(&mut self) {
+        let (pair, key) = self.key.as_ref().unwrap();
+        let RData::Dnskey(dnskey) = key.rdata() else { unreachable!() };
+        let mut sets: Vec<Vec<Answer>> = vec![];
+        for r in self.records.iter().filter(|r| self.is_authoritative(r)) {
+            match sets.iter_mut().find(|s| s[0].name() == r.name() && s[0].ty() == r.ty()) {
+                Some(set) => set.push(r.clone()),
+                None => sets.push(vec![r.clone()]),
+            }
+        }
+        for set in sets {
+            let rrsig = Rrsig {
+                type_covered: set[0].ty(),
+                algorithm: Algorithm::ED25519,
+                labels: set[0].name().label_count() as u8,
+                original_ttl: set[0].ttl(),
+                expiration: NOW + 86400,
+                inception: NOW - 86400,
+                key_tag: dnskey.key_tag(),
+                signer_name: self.apex.clone(),
+                signature: vec![],
+            };
+            let rrsig = sign_rrset(&set, rrsig, |data| pair.sign(data).as_ref().to_vec()).unwrap();
+            self.records.push(Answer::new(set[0].name().clone(), Qclass::IN, 3600, RData::Rrsig(rrsig)));
+        }
+    }
+
+    // Changes a record after it was signed, leaving its signature in place
+    fn tamper(&mut self, owner: &str, rdata: RData) {
+        let owner = name(owner);
+        let record = self.records.iter_mut().find(|r| *r.name() == owner && r.ty() == rdata.qtype()).unwrap();
+        *record = Answer::new(owner, Qclass::IN, record.ttl(), rdata);
+    }
+
+    fn rrset(&self, owner: &Name, ty: Qtype) -> Vec<Answer> {
+        let covers = |r: &Answer| matches!(r.rdata(), RData::Rrsig(sig) if sig.type_covered == ty);
+        self.records.iter()
+            .filter(|r| r.name() == owner && (r.ty() == ty || covers(r)))
+            .cloned()
+            .collect()
+    }
+
+    // Every NSEC or NSEC3 record with its signature, the validator picks the ones it needs
+    fn denial(&self) -> Vec<Answer> {
+        let chain = |ty: Qtype| ty == Qtype::NSEC || ty == Qtype::NSEC3;
+        self.records.iter()
+            .filter(|r| chain(r.ty()) || matches!(r.rdata(), RData::Rrsig(sig) if chain(sig.type_covered)))
+            .cloned()
+            .collect()
+    }
+}
+
+// Answers the way an authoritative server for `zones` would, following CNAMEs between them
+fn answer(zones: &[Zone], query: &Message) -> Message {
+    let mut response = Message::response_to(query);
+    let question = &query.questions()[0];
+    let mut qname = question.qname().clone();
+    let qtype = question.qtype();
+    // The DS at a cut is the parent's
+    let serving = |n: &Name| zones.iter()
+        .filter(|z| n.is_subdomain_of(&z.apex) && !(qtype == Qtype::DS && *n == z.apex))
+        .max_by_key(|z| z.apex.label_count());
+    let Some(mut zone) = serving(&qname) else {
+        response.set_rcode(Rcode::Refused);
+        return response;
+    };
+    if let Some(cut) = zone.cut(&qname).filter(|cut| !(qtype == Qtype::DS && *cut == qname)) {
+        let ns = zone.rrset(&cut, Qtype::NS);
+        for r in ns.iter() {
+            response.add_authority(r.clone());
+        }
+        let ds = zone.rrset(&cut, Qtype::DS);
+        let proof = if ds.is_empty() { zone.denial() } else { ds };
+        for r in proof {
+            response.add_authority(r);
+        }
+        for r in ns.iter() {
+            let RData::Ns(target) = r.rdata() else { continue };
+            for glue in zone.rrset(target, Qtype::A) {
+                response.add_additional(glue);
+            }
+        }
+        return response;
+    }
+    let mut flags = response.flags();
+    flags.aa = true;
+    response.set_flags(flags);
+    loop {
+        let found = zone.rrset(&qname, qtype);
+        if !found.is_empty() {
+            for r in found {
+                response.add_answer(r);
+            }
+            return response;
+        }
+        let cname = zone.rrset(&qname, Qtype::CNAME);
+        let target = cname.iter().find_map(|r| match r.rdata() {
+            RData::Cname(target) => Some(target.clone()),
+            _ => None,
+        });
+        if let Some(target) = target {
+            for r in cname {
+                response.add_answer(r);
+            }
+            match serving(&target) {
+                Some(next) => zone = next,
+                None => return response,
+            }
+            qname = target;
+            continue;
+        }
+        if !zone.records.iter().any(|r| *r.name() == qname) {
+            response.set_rcode(Rcode::NameError);
+        }
+        for r in zone.rrset(&zone.apex, Qtype::SOA).into_iter().chain(zone.denial()) {
+            response.add_authority(r);
+        }
+        return response;
+    }
+}
+
+fn serve(socket: UdpSocket, zones: Vec<Zone>) {
+    thread::spawn(move || {
+        let mut buf = [0; 65535];
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            let Ok(query) = Message::deserialize(&buf[..len]) else { continue };
+            let _ = socket.send_to(&answer(&zones, &query).to_vec().unwrap(), from);
+        }
+    });
+}
+
+// Starts the servers and returns a resolver that trusts the stand-in root's key
+fn network() -> Resolver {
+    let mut signed = Zone::new("signed.test.", SIGNED).signed(3, Chain::Nsec);
+    signed.tamper("forged.signed.test.", RData::A(Ipv4Addr::new(192, 0, 2, 66)));
+    let broken = Zone::new("broken.test.", BROKEN).signed(4, Chain::Nsec);
+    // The parent publishes a DS for a key the child doesn't use
+    let stale = Zone::new("broken.test.", BROKEN).signed(5, Chain::Nsec).ds();
+    // Signed, but the parent only has a DS with a digest we can't check
+    let gost = Zone::new("gost.test.", GOST).signed(6, Chain::Nsec);
+    let mut gost_ds = gost.ds();
+    if let RData::Ds(ds) = gost_ds.rdata() {
+        gost_ds = Answer::new(gost.apex.clone(), Qclass::IN, 3600, RData::Ds(Ds { digest_type: DigestType::GOST, ..ds.clone() }));
+    }
+    let mut test = Zone::new("test.", TEST);
+    test.records.extend([signed.ds(), stale, gost_ds]);
+    let test = test.signed(2, Chain::Nsec3);
+    let mut root = Zone::new(".", ROOT);
+    root.records.push(test.ds());
+    let root = root.signed(1, Chain::Nsec);
+    let anchor = root.ds();
+
+    let root_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tld_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let leaf_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let root_addr = root_socket.local_addr().unwrap();
+    let tld_addr = tld_socket.local_addr().unwrap();
+    let leaf_addr = leaf_socket.local_addr().unwrap();
+    serve(root_socket, vec![root]);
+    serve(tld_socket, vec![test]);
+    serve(leaf_socket, vec![
+        signed,
+        broken,
+        gost,
+        Zone::new("unsigned.test.", UNSIGNED),
+        Zone::new("plain.", PLAIN),
+    ]);
+    Resolver::new(vec![root_addr])
+        .redirect(IpAddr::V4(TLD), tld_addr)
+        .redirect(IpAddr::V4(LEAF), leaf_addr)
+        .timeout(Duration::from_secs(2))
+        .now(NOW)
+        .trust_anchor(&anchor)
+}
+
+fn addresses(response: &Response) -> Vec<Ipv4Addr> {
+    response.message.answers().iter()
+        .filter_map(|r| match r.rdata() {
+            RData::A(addr) => Some(*addr),
+            _ => None,
+        })
+        .collect()
+}
+
+fn types(response: &Response) -> Vec<Qtype> {
+    response.message.answers().iter().map(|r| r.ty()).filter(|&ty| ty != Qtype::RRSIG).collect()
+}
+
+#[test]
+fn secure() {
+    let resolver = network();
+    let response = resolver.resolve("www.signed.test.", Qtype::A).unwrap();
+    assert_eq!(response.security, Security::Secure);
+    assert_eq!(addresses(&response), [Ipv4Addr::new(192, 0, 2, 1)]);
+    let response = resolver.resolve("alias.signed.test.", Qtype::A).unwrap();
+    assert_eq!(response.security, Security::Secure);
+    assert_eq!(types(&response), [Qtype::CNAME, Qtype::A]);
+}
+
+#[test]
+fn bogus() {
+    let resolver = network();
+    let response = resolver.resolve("forged.signed.test.", Qtype::A).unwrap();
+    assert_eq!(response.security, Security::Bogus(ValidationError::BadSignature));
+    let response = resolver.resolve("www.broken.test.", Qtype::A).unwrap();
+    assert_eq!(response.security, Security::Bogus(ValidationError::DsMismatch));
+}
+
+#[test]
+fn insecure_delegations() {
+    let resolver = network();
+    // Proven unsigned with NSEC by the root and with NSEC3 by test., or with no DS we can check
+    for qname in ["www.plain.", "www.unsigned.test.", "www.gost.test."] {
+        let response = resolver.resolve(qname, Qtype::A).unwrap();
+        assert_eq!(response.security, Security::Insecure, "{}", qname);
+        assert_eq!(addresses(&response).len(), 1);
+    }
+}
+
+#[test]
+fn authenticated_denial() {
+    let resolver = network();
+    let response = resolver.resolve("nope.signed.test.", Qtype::A).unwrap();
+    assert_eq!(response.message.rcode(), Rcode::NameError);
+    assert_eq!(response.security, Security::Secure);
+    let response = resolver.resolve("www.signed.test.", Qtype::AAAA).unwrap();
+    assert_eq!(response.message.rcode(), Rcode::NoError);
+    assert!(response.message.answers().is_empty());
+    assert_eq!(response.security, Security::Secure);
+    // NXDOMAIN in the NSEC3 signed zone, checked on the way to a name below a delegation
+    let response = resolver.resolve("nope.test.", Qtype::A).unwrap();
+    assert_eq!(response.message.rcode(), Rcode::NameError);
+    assert_eq!(response.security, Security::Secure);
+}
+
+#[test]
+fn chain_out_of_zone() {
+    // The leaf server serves unsigned.test. too and adds its unsigned A record to the answer,
+    // which is followed from that zone's own delegation instead of passing as signed.test.'s
+    let resolver = network();
+    let response = resolver.resolve("away.signed.test.", Qtype::A).unwrap();
+    assert_eq!(response.security, Security::Insecure);
+    assert_eq!(types(&response), [Qtype::CNAME, Qtype::A]);
+    assert_eq!(addresses(&response), [Ipv4Addr::new(192, 0, 2, 4)]);
+}
+
+#[test]
+fn exchange_skips_garbage() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let (len, from) = server.recv_from(&mut buf).unwrap();
+        let query = Message::deserialize(&buf[..len]).unwrap();
+        server.send_to(&[0xff; 5], from).unwrap();
+        server.send_to(&Message::response_to(&query).to_vec().unwrap(), from).unwrap();
+    });
+    let query = Message::builder().id(7).question("example.", Qtype::A, Qclass::IN).build().unwrap();
+    let response = exchange(addr, &query, Duration::from_secs(2)).unwrap();
+    assert_eq!(response.id(), 7);
+}
+
+#[test]
+fn junk_times_out() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap();
+    // Keeps answering with garbage, none of which ends the wait on its own
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let Ok((_, from)) = socket.recv_from(&mut buf) else { return };
+        for _ in 0..500 {
+            let _ = socket.send_to(&[0xff; 12], from);
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+    let query = Message::builder().id(1).question("www.test.", Qtype::A, Qclass::IN).build().unwrap();
+    let start = Instant::now();
+    let error = exchange(server, &query, Duration::from_millis(200)).unwrap_err();
+    assert!(matches!(error, ResolveError::Io(ref e) if e.kind() == std::io::ErrorKind::TimedOut), "{}", error);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}